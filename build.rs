@@ -1,3 +1,39 @@
+use std::{env, fs, path::Path};
+
 fn main() {
     println!("cargo:rustc-link-search={}", "linkscript");
+
+    /* 64 hex digits, the attestation key of the device coffer is built for */
+    println!("cargo:rerun-if-env-changed=COFFER_DEVICE_SEED");
+    let seed = match env::var("COFFER_DEVICE_SEED") {
+        Ok(hex) => {
+            assert!(hex.len() == 64, "COFFER_DEVICE_SEED must be 32 bytes of hex");
+            (0..32)
+                .map(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap())
+                .collect::<Vec<_>>()
+        }
+        /* a firmware everyone can impersonate, never without asking for it */
+        Err(_) if env::var_os("CARGO_FEATURE_DEV_KEY").is_some() => {
+            println!("cargo:warning=dev-key: using the all-zero development attestation key");
+            vec![0u8; 32]
+        }
+        Err(_) => panic!("COFFER_DEVICE_SEED is not set, enable the `dev-key` feature to build with the all-zero development key"),
+    };
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("device_seed.rs");
+    fs::write(out, format!("{:?}", seed)).unwrap();
+
+    /* colon separated .dtbo files applied over the boot device tree, in order */
+    println!("cargo:rerun-if-env-changed=COFFER_OVERLAYS");
+    let overlays = env::var("COFFER_OVERLAYS").unwrap_or_default();
+    let entries = overlays
+        .split(':')
+        .filter(|path| !path.is_empty())
+        .map(|path| {
+            let path = fs::canonicalize(path).expect("COFFER_OVERLAYS names a missing file");
+            println!("cargo:rerun-if-changed={}", path.display());
+            format!("&Aligned(*include_bytes!({:?})).0,", path)
+        })
+        .collect::<String>();
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("overlays.rs");
+    fs::write(out, format!("[{}]", entries)).unwrap();
 }
@@ -0,0 +1,94 @@
+use core::convert::TryInto;
+
+/* a bundle is this header followed by the ELF64 image at `elf_offset` */
+pub const BUNDLE_MAGIC: [u8; 8] = *b"COFFERB2";
+pub const MANIFEST_SIZE: usize = 7 * 8;
+pub const HEADER_SIZE: usize = 8 + MANIFEST_SIZE + 2 * 8;
+
+/* how the enclave wants to be laid out, addresses are ELF virtual addresses */
+#[derive(Clone, Copy)]
+pub struct Manifest {
+    /* power of two, the whole private region */
+    pub memory_size: u64,
+    /* per thread, stacks are carved from the top of the region */
+    pub stack_size: u64,
+    pub entry: u64,
+    pub threads: u64,
+    /* zero when the enclave handles no exception itself */
+    pub trap_handler: u64,
+    pub trap_delegated: u64,
+    /* scrubbing on exit the enclave does without, zero keeps all of it: FP 1 << 0,
+     * vector 1 << 1, TLB 1 << 2, branch predictors 1 << 3, D-cache 1 << 4 */
+    pub scrub_exempt: u64,
+}
+
+pub struct BundleHeader {
+    pub manifest: Manifest,
+    pub elf_offset: u64,
+    pub elf_size: u64,
+}
+
+fn read_u64(bytes: &[u8], idx: usize) -> u64 {
+    u64::from_le_bytes(bytes[idx * 8..idx * 8 + 8].try_into().unwrap())
+}
+
+impl Manifest {
+    /* little endian, in field order; this is also what gets measured */
+    pub fn to_bytes(&self) -> [u8; MANIFEST_SIZE] {
+        let fields = [
+            self.memory_size,
+            self.stack_size,
+            self.entry,
+            self.threads,
+            self.trap_handler,
+            self.trap_delegated,
+            self.scrub_exempt,
+        ];
+        let mut bytes = [0u8; MANIFEST_SIZE];
+        for (chunk, field) in bytes.chunks_mut(8).zip(fields.iter()) {
+            chunk.copy_from_slice(&field.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Manifest {
+            memory_size: read_u64(bytes, 0),
+            stack_size: read_u64(bytes, 1),
+            entry: read_u64(bytes, 2),
+            threads: read_u64(bytes, 3),
+            trap_handler: read_u64(bytes, 4),
+            trap_delegated: read_u64(bytes, 5),
+            scrub_exempt: read_u64(bytes, 6),
+        }
+    }
+}
+
+impl BundleHeader {
+    pub fn parse(bundle: &[u8]) -> Result<Self, &'static str> {
+        if bundle.len() < HEADER_SIZE || bundle[..8] != BUNDLE_MAGIC {
+            return Err("[ERROR]: not an enclave bundle");
+        }
+        let manifest = Manifest::from_bytes(&bundle[8..8 + MANIFEST_SIZE]);
+        let tail = &bundle[8 + MANIFEST_SIZE..HEADER_SIZE];
+        let header = BundleHeader {
+            manifest,
+            elf_offset: read_u64(tail, 0),
+            elf_size: read_u64(tail, 1),
+        };
+        match header.elf_offset.checked_add(header.elf_size) {
+            Some(end) if end <= bundle.len() as u64 => Ok(header),
+            _ => Err("[ERROR]: elf image runs past the bundle"),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0u8; HEADER_SIZE];
+        bytes[..8].copy_from_slice(&BUNDLE_MAGIC);
+        bytes[8..8 + MANIFEST_SIZE].copy_from_slice(&self.manifest.to_bytes());
+        bytes[8 + MANIFEST_SIZE..8 + MANIFEST_SIZE + 8]
+            .copy_from_slice(&self.elf_offset.to_le_bytes());
+        bytes[8 + MANIFEST_SIZE + 8..].copy_from_slice(&self.elf_size.to_le_bytes());
+        bytes
+    }
+}
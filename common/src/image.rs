@@ -0,0 +1,126 @@
+use core::ops::Range;
+
+use alloc::vec::Vec;
+use goblin::elf::{header::EM_RISCV, program_header, Elf};
+
+use crate::bundle::{BundleHeader, Manifest};
+use crate::measure::{measure, Measurement};
+
+pub const PAGE_SIZE: usize = 4096;
+/* every thread is created at load time and keeps its stack for good */
+pub const MAX_THREADS: u64 = 64;
+
+/* one PT_LOAD, offsets are relative to the start of the enclave region */
+pub struct Segment {
+    pub offset: usize,
+    pub file: Range<usize>,
+    pub mem_size: usize,
+    /* PF_R / PF_W / PF_X */
+    pub flags: u32,
+}
+
+/* a checked image, ready to be copied into `manifest.memory_size` bytes */
+pub struct Image<'a> {
+    pub manifest: Manifest,
+    pub elf: &'a [u8],
+    pub segments: Vec<Segment>,
+    pub entry: usize,
+    pub trap_handler: Option<usize>,
+}
+
+impl<'a> Image<'a> {
+    /* segments are placed relative to the lowest PT_LOAD, images must be position independent */
+    pub fn parse(bundle: &'a [u8]) -> Result<Self, &'static str> {
+        let header = BundleHeader::parse(bundle)?;
+        let manifest = header.manifest;
+        let elf_start = header.elf_offset as usize;
+        let elf_bytes = &bundle[elf_start..elf_start + header.elf_size as usize];
+        let elf = Elf::parse(elf_bytes).map_err(|_| "[ERROR]: malformed elf")?;
+        if !elf.is_64 || elf.header.e_machine != EM_RISCV {
+            return Err("[ERROR]: not a RISC-V ELF64 image");
+        }
+        let memory_size = manifest.memory_size as usize;
+        if !memory_size.is_power_of_two() || memory_size < PAGE_SIZE {
+            return Err("[ERROR]: memory size is not a power of two");
+        }
+        let loads: Vec<_> = elf
+            .program_headers
+            .iter()
+            .filter(|ph| ph.p_type == program_header::PT_LOAD)
+            .collect();
+        let image_base = loads
+            .iter()
+            .map(|ph| ph.p_vaddr as usize & !(PAGE_SIZE - 1))
+            .min()
+            .ok_or("[ERROR]: image has nothing to load")?;
+        if manifest.stack_size == 0 {
+            return Err("[ERROR]: threads need a stack");
+        }
+        if manifest.threads == 0 || manifest.threads > MAX_THREADS {
+            return Err("[ERROR]: thread count out of range");
+        }
+        /* stacks take the top of the region */
+        let stacks = (manifest.stack_size as usize)
+            .checked_mul(manifest.threads as usize)
+            .filter(|stacks| *stacks < memory_size)
+            .ok_or("[ERROR]: stacks do not fit")?;
+        let mut segments = Vec::new();
+        for ph in loads {
+            let offset = ph.p_vaddr as usize - image_base;
+            let file_end = (ph.p_offset as usize).checked_add(ph.p_filesz as usize);
+            let mem_end = offset.checked_add(ph.p_memsz as usize);
+            match (file_end, mem_end) {
+                (Some(file_end), Some(mem_end))
+                    if ph.p_filesz <= ph.p_memsz
+                        && file_end <= elf_bytes.len()
+                        && mem_end <= memory_size - stacks => {}
+                _ => return Err("[ERROR]: segment out of bounds"),
+            }
+            segments.push(Segment {
+                offset,
+                file: ph.p_offset as usize..ph.p_offset as usize + ph.p_filesz as usize,
+                mem_size: ph.p_memsz as usize,
+                flags: ph.p_flags,
+            });
+        }
+        /* a vaddr inside an executable segment, as an offset */
+        let code_offset = |vaddr: u64| {
+            let offset = (vaddr as usize).checked_sub(image_base)?;
+            segments
+                .iter()
+                .find(|s| {
+                    s.flags & program_header::PF_X != 0
+                        && (s.offset..s.offset + s.mem_size).contains(&offset)
+                })
+                .map(|_| offset)
+        };
+        let entry = code_offset(manifest.entry).ok_or("[ERROR]: entry is not in code")?;
+        let trap_handler = match manifest.trap_handler {
+            0 => None,
+            handler => Some(code_offset(handler).ok_or("[ERROR]: trap handler is not in code")?),
+        };
+        Ok(Image {
+            manifest,
+            elf: elf_bytes,
+            segments,
+            entry,
+            trap_handler,
+        })
+    }
+
+    /* `region` is the whole enclave memory, everything outside the segments is zeroed */
+    pub fn load_into(&self, region: &mut [u8]) {
+        for byte in region.iter_mut() {
+            *byte = 0;
+        }
+        for segment in self.segments.iter() {
+            let file = &self.elf[segment.file.clone()];
+            region[segment.offset..segment.offset + file.len()].copy_from_slice(file);
+        }
+    }
+
+    /* `region` as left by `load_into` */
+    pub fn measure(&self, region: &[u8]) -> Measurement {
+        measure(region, self.entry, &self.manifest.to_bytes())
+    }
+}
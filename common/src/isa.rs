@@ -0,0 +1,196 @@
+use alloc::{string::String, vec::Vec};
+
+/* ISA extensions coffer may care about; the letters share misa's bit numbers */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum Ext {
+    A = 0,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Zicsr,
+    Zifencei,
+    Zicbom,
+    Zicboz,
+    Zicbop,
+    Zihintpause,
+    Zba,
+    Zbb,
+    Zbs,
+    Zkr,
+    Smepmp,
+    Smstateen,
+    Sstc,
+    Sscofpmf,
+    Svinval,
+    Svnapot,
+    Svpbmt,
+    XTheadCmo,
+    XTheadSync,
+}
+
+/* multi-letter names, lower case as they appear in the device tree */
+const MULTI_LETTER: &[(&str, Ext)] = &[
+    ("zicsr", Ext::Zicsr),
+    ("zifencei", Ext::Zifencei),
+    ("zicbom", Ext::Zicbom),
+    ("zicboz", Ext::Zicboz),
+    ("zicbop", Ext::Zicbop),
+    ("zihintpause", Ext::Zihintpause),
+    ("zba", Ext::Zba),
+    ("zbb", Ext::Zbb),
+    ("zbs", Ext::Zbs),
+    ("zkr", Ext::Zkr),
+    ("smepmp", Ext::Smepmp),
+    ("smstateen", Ext::Smstateen),
+    ("sstc", Ext::Sstc),
+    ("sscofpmf", Ext::Sscofpmf),
+    ("svinval", Ext::Svinval),
+    ("svnapot", Ext::Svnapot),
+    ("svpbmt", Ext::Svpbmt),
+    ("xtheadcmo", Ext::XTheadCmo),
+    ("xtheadsync", Ext::XTheadSync),
+];
+
+/* what one hart implements */
+#[derive(Clone, Default)]
+pub struct FeatureSet {
+    bits: u64,
+    /* multi-letter extensions coffer has no `Ext` for, vendor X* ones included */
+    others: Vec<String>,
+}
+
+impl FeatureSet {
+    pub fn has(&self, ext: Ext) -> bool {
+        self.bits & (1 << ext as u8) != 0
+    }
+
+    pub fn insert(&mut self, ext: Ext) {
+        self.bits |= 1 << ext as u8;
+    }
+
+    /* forget every single letter, for when misa knows better */
+    pub fn clear_letters(&mut self) {
+        self.bits &= !((1 << 26) - 1);
+    }
+
+    /* a letter from 'a' to 'z', either case */
+    pub fn insert_letter(&mut self, letter: char) {
+        let letter = letter.to_ascii_lowercase();
+        if letter.is_ascii_lowercase() {
+            self.bits |= 1 << (letter as u8 - b'a');
+        }
+        /* g is shorthand for imafd with zicsr and zifencei */
+        if letter == 'g' {
+            for ext in [Ext::I, Ext::M, Ext::A, Ext::F, Ext::D, Ext::Zicsr, Ext::Zifencei] {
+                self.insert(ext);
+            }
+        }
+    }
+
+    /* one extension name without version, e.g. "c", "zicbom" or "xtheadvector" */
+    pub fn insert_name(&mut self, name: &str) {
+        let name = name.to_ascii_lowercase();
+        let mut letters = name.chars();
+        match (letters.next(), letters.next()) {
+            (Some(letter), None) => self.insert_letter(letter),
+            _ => match MULTI_LETTER.iter().find(|(known, _)| *known == name) {
+                Some((_, ext)) => self.insert(*ext),
+                None if !self.others.contains(&name) => self.others.push(name),
+                None => {}
+            },
+        }
+    }
+
+    pub fn letters(&self) -> impl Iterator<Item = char> + '_ {
+        ('a'..='z').filter(move |c| self.bits & (1 << (*c as u8 - b'a')) != 0)
+    }
+
+    /* every multi-letter extension, known or not */
+    pub fn multi_letter(&self) -> impl Iterator<Item = &str> + '_ {
+        MULTI_LETTER
+            .iter()
+            .filter(move |(_, ext)| self.has(*ext))
+            .map(|(name, _)| *name)
+            .chain(self.others.iter().map(|name| name.as_str()))
+    }
+}
+
+/* "2p1", "2", or nothing after an extension name */
+fn strip_version(name: &str) -> &str {
+    let name = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    match name.strip_suffix('p') {
+        Some(major) if major.ends_with(|c: char| c.is_ascii_digit()) => {
+            major.trim_end_matches(|c: char| c.is_ascii_digit())
+        }
+        _ => name,
+    }
+}
+
+/* length of a "2" or "2p1" version at the start of `bytes` */
+fn version_len(bytes: &[u8]) -> usize {
+    let digits = |bytes: &[u8]| bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+    let major = digits(bytes);
+    match bytes.get(major) {
+        Some(b'p') if major > 0 && digits(&bytes[major + 1..]) > 0 => {
+            major + 1 + digits(&bytes[major + 1..])
+        }
+        _ => major,
+    }
+}
+
+/* riscv,isa: "rv64imafdc_zicsr_zifencei_sstc", letters may carry versions like "i2p1" */
+pub fn parse_isa(isa: &str) -> Option<FeatureSet> {
+    let isa = isa.to_ascii_lowercase();
+    let rest = isa
+        .strip_prefix("rv64")
+        .or_else(|| isa.strip_prefix("rv32"))?;
+    let bytes = rest.as_bytes();
+    let mut features = FeatureSet::default();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'_' => i += 1,
+            /* multi-letter extensions run up to the next underscore */
+            b's' | b'x' | b'z' => {
+                let end = rest[i..].find('_').map_or(rest.len(), |len| i + len);
+                let name = strip_version(&rest[i..end]);
+                /* old trees write supervisor and user mode as "su" among the letters */
+                match name {
+                    "s" | "su" => name.chars().for_each(|c| features.insert_letter(c)),
+                    _ => features.insert_name(name),
+                }
+                i = end;
+            }
+            c if c.is_ascii_lowercase() => {
+                features.insert_letter(c as char);
+                i += 1 + version_len(&bytes[i + 1..]);
+            }
+            _ => return None,
+        }
+    }
+    Some(features)
+}
+
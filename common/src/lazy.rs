@@ -0,0 +1,55 @@
+/* lazy FP/vector switching, decided on mstatus.FS and mstatus.VS alone */
+
+/* the two bit encoding FS and VS share */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnitState {
+    Off = 0,
+    Initial = 1,
+    Clean = 2,
+    Dirty = 3,
+}
+
+impl UnitState {
+    pub fn from_bits(bits: usize) -> Self {
+        match bits & 0b11 {
+            0 => UnitState::Off,
+            1 => UnitState::Initial,
+            2 => UnitState::Clean,
+            _ => UnitState::Dirty,
+        }
+    }
+
+    pub fn bits(self) -> usize {
+        self as usize
+    }
+}
+
+/* what entering a runtime does to the live registers of one unit */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OnEntry {
+    /* they already are the runtime's */
+    Keep,
+    /* the runtime may use the unit, it gets its own copy */
+    Load,
+    /* left alone, an Off runtime pays nothing; yet it may turn the unit on without a trap
+     * and clobber them, so nobody may count on them being theirs afterwards */
+    Disown,
+}
+
+/* `live_here`: the registers hold the runtime's state as of its last trap on this hart */
+pub fn on_entry(state: UnitState, live_here: bool) -> OnEntry {
+    match (state, live_here) {
+        (_, true) => OnEntry::Keep,
+        (UnitState::Off, false) => OnEntry::Disown,
+        _ => OnEntry::Load,
+    }
+}
+
+/* after a trap: only what the runtime changed is saved, the state to store back into its
+ * mstatus is returned when it was */
+pub fn save_on_exit(state: UnitState) -> Option<UnitState> {
+    match state {
+        UnitState::Dirty => Some(UnitState::Clean),
+        _ => None,
+    }
+}
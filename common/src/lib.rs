@@ -0,0 +1,12 @@
+#![no_std]
+
+extern crate alloc;
+
+pub mod bundle;
+pub mod image;
+pub mod isa;
+pub mod lazy;
+pub mod measure;
+pub mod pool;
+pub mod report;
+pub mod seal;
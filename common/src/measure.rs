@@ -0,0 +1,13 @@
+use sha2::{Digest, Sha256};
+
+pub type Measurement = [u8; 32];
+
+/* hash of the initial image, where execution starts and the load configuration */
+pub fn measure(image: &[u8], entry_offset: usize, config: &[u8]) -> Measurement {
+    let mut hasher = Sha256::new();
+    hasher.update(&(image.len() as u64).to_le_bytes());
+    hasher.update(&(entry_offset as u64).to_le_bytes());
+    hasher.update(config);
+    hasher.update(image);
+    hasher.finalize().into()
+}
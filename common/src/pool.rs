@@ -0,0 +1,213 @@
+use core::ops::Range;
+
+use alloc::vec::Vec;
+
+pub const PAGE_SIZE: usize = 4096;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Owner {
+    Free,
+    Enclave(usize),
+}
+
+/* naturally aligned power-of-two block, so any chunk maps to one napot entry */
+#[derive(Clone, Copy)]
+struct Chunk {
+    addr: usize,
+    len: usize,
+    owner: Owner,
+}
+
+impl Chunk {
+    fn range(&self) -> Range<usize> {
+        self.addr..self.addr + self.len
+    }
+}
+
+/* physical memory the S-mode host handed to coffer, split buddy-style */
+#[derive(Default)]
+pub struct MemoryPool {
+    chunks: Vec<Chunk>,
+}
+
+impl MemoryPool {
+    pub const fn new() -> Self {
+        MemoryPool { chunks: Vec::new() }
+    }
+
+    pub fn overlaps(&self, range: &Range<usize>) -> bool {
+        self.chunks
+            .iter()
+            .any(|c| c.addr < range.end && range.start < c.addr + c.len)
+    }
+
+    pub fn owner(&self, addr: usize) -> Option<Owner> {
+        self.chunks
+            .iter()
+            .find(|c| c.range().contains(&addr))
+            .map(|c| c.owner)
+    }
+
+    /* registered memory as contiguous ranges, whoever holds it */
+    pub fn ranges(&self) -> Vec<Range<usize>> {
+        let mut chunks: Vec<_> = self.chunks.iter().map(|c| c.range()).collect();
+        chunks.sort_by_key(|r| r.start);
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for chunk in chunks {
+            match ranges.last_mut() {
+                Some(last) if last.end == chunk.start => last.end = chunk.end,
+                _ => ranges.push(chunk),
+            }
+        }
+        ranges
+    }
+
+    /* page aligned ranges are cut into the largest aligned blocks */
+    pub fn register(&mut self, base: usize, size: usize) -> Result<(), &'static str> {
+        if base % PAGE_SIZE != 0 || size % PAGE_SIZE != 0 || size == 0 {
+            return Err("[ERROR]: pool range is not page aligned");
+        }
+        let end = base.checked_add(size).ok_or("[ERROR]: pool range overflows")?;
+        if self.overlaps(&(base..end)) {
+            return Err("[ERROR]: pool range already registered");
+        }
+        let mut addr = base;
+        while addr < end {
+            let mut len = if addr == 0 {
+                1 << (usize::BITS - 1)
+            } else {
+                1 << addr.trailing_zeros()
+            };
+            while addr.checked_add(len).map_or(true, |top| top > end) {
+                len >>= 1;
+            }
+            self.insert_chunk(Chunk {
+                addr,
+                len,
+                owner: Owner::Free,
+            });
+            addr += len;
+        }
+        Ok(())
+    }
+
+    /* smallest free block that fits, halved down to `len` */
+    pub fn alloc(&mut self, len: usize, owner: Owner) -> Option<usize> {
+        if len < PAGE_SIZE || !len.is_power_of_two() {
+            return None;
+        }
+        let idx = self
+            .chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.owner == Owner::Free && c.len >= len)
+            .min_by_key(|(_, c)| c.len)
+            .map(|(idx, _)| idx)?;
+        let mut chunk = self.chunks.remove(idx);
+        while chunk.len > len {
+            chunk.len >>= 1;
+            self.chunks.push(Chunk {
+                addr: chunk.addr + chunk.len,
+                ..chunk
+            });
+        }
+        chunk.owner = owner;
+        self.chunks.push(chunk);
+        Some(chunk.addr)
+    }
+
+    /* cut chunks so that `range` starts and ends on chunk boundaries; pages are the
+     * smallest unit, anything else would cut chunks down to single bytes */
+    fn split_at(&mut self, range: &Range<usize>) -> bool {
+        if range.start % PAGE_SIZE != 0 || range.end % PAGE_SIZE != 0 {
+            return false;
+        }
+        for bound in [range.start, range.end].iter() {
+            while let Some(idx) = self
+                .chunks
+                .iter()
+                .position(|c| c.addr < *bound && *bound < c.addr + c.len)
+            {
+                let chunk = &mut self.chunks[idx];
+                chunk.len >>= 1;
+                let upper = Chunk {
+                    addr: chunk.addr + chunk.len,
+                    ..*chunk
+                };
+                self.chunks.push(upper);
+            }
+        }
+        true
+    }
+
+    /* true when all of `range` is pool memory held by `owner` */
+    fn held_by(&self, range: &Range<usize>, owner: Owner) -> bool {
+        let covered: usize = self
+            .chunks
+            .iter()
+            .filter(|c| range.start <= c.addr && c.addr + c.len <= range.end)
+            .map(|c| if c.owner == owner { c.len } else { 0 })
+            .sum();
+        covered == range.end - range.start
+    }
+
+    /* give back whatever part of `range` `owner` holds, the rest is not pool memory;
+     * `range` has to be page aligned */
+    pub fn free(&mut self, range: Range<usize>, owner: Owner) {
+        if !self.split_at(&range) {
+            return;
+        }
+        for chunk in self.chunks.iter_mut() {
+            if chunk.owner == owner && range.start <= chunk.addr && chunk.range().end <= range.end {
+                chunk.owner = Owner::Free;
+            }
+        }
+        self.coalesce();
+    }
+
+    /* on destroy everything the enclave still holds returns to the pool */
+    pub fn free_all(&mut self, eid: usize) {
+        for chunk in self.chunks.iter_mut() {
+            if chunk.owner == Owner::Enclave(eid) {
+                chunk.owner = Owner::Free;
+            }
+        }
+        self.coalesce();
+    }
+
+    /* hand free pages back to the host for good */
+    pub fn reclaim(&mut self, range: Range<usize>) -> bool {
+        if !self.split_at(&range) {
+            return false;
+        }
+        if !self.held_by(&range, Owner::Free) {
+            self.coalesce();
+            return false;
+        }
+        self.chunks
+            .retain(|c| !(range.start <= c.addr && c.addr + c.len <= range.end));
+        true
+    }
+
+    /* merge buddies left behind by splits, held ones too so a partial free does not leave
+     * the rest of an enclave's chunk in pieces for good */
+    fn coalesce(&mut self) {
+        let chunks = core::mem::take(&mut self.chunks);
+        for chunk in chunks {
+            self.insert_chunk(chunk);
+        }
+    }
+
+    /* insert a chunk, merging with its buddy of the same owner while possible */
+    fn insert_chunk(&mut self, chunk: Chunk) {
+        let mut chunk = chunk;
+        while let Some(idx) = self.chunks.iter().position(|c| {
+            c.owner == chunk.owner && c.len == chunk.len && c.addr == chunk.addr ^ chunk.len
+        }) {
+            self.chunks.remove(idx);
+            chunk.addr &= !chunk.len;
+            chunk.len <<= 1;
+        }
+        self.chunks.push(chunk);
+    }
+}
@@ -0,0 +1,50 @@
+use core::convert::TryInto;
+
+use crate::measure::Measurement;
+
+pub const REPORT_DATA_SIZE: usize = 64;
+pub const SIGNATURE_SIZE: usize = 64;
+pub const REPORT_SIZE: usize = 32 + REPORT_DATA_SIZE + SIGNATURE_SIZE;
+const REPORT_DOMAIN: &[u8] = b"coffer-report-v1";
+pub const SIGNED_SIZE: usize = REPORT_DOMAIN.len() + 32 + REPORT_DATA_SIZE;
+
+/* what an enclave gets back from attestation, signed with the device key */
+pub struct Report {
+    pub measurement: Measurement,
+    /* chosen by the enclave, typically a verifier nonce or a key hash */
+    pub report_data: [u8; REPORT_DATA_SIZE],
+    pub signature: [u8; SIGNATURE_SIZE],
+}
+
+impl Report {
+    /* the exact bytes covered by `signature` */
+    pub fn signed_bytes(
+        measurement: &Measurement,
+        report_data: &[u8; REPORT_DATA_SIZE],
+    ) -> [u8; SIGNED_SIZE] {
+        let mut bytes = [0u8; SIGNED_SIZE];
+        bytes[..REPORT_DOMAIN.len()].copy_from_slice(REPORT_DOMAIN);
+        bytes[REPORT_DOMAIN.len()..REPORT_DOMAIN.len() + 32].copy_from_slice(measurement);
+        bytes[REPORT_DOMAIN.len() + 32..].copy_from_slice(report_data);
+        bytes
+    }
+
+    pub fn to_bytes(&self) -> [u8; REPORT_SIZE] {
+        let mut bytes = [0u8; REPORT_SIZE];
+        bytes[..32].copy_from_slice(&self.measurement);
+        bytes[32..32 + REPORT_DATA_SIZE].copy_from_slice(&self.report_data);
+        bytes[32 + REPORT_DATA_SIZE..].copy_from_slice(&self.signature);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != REPORT_SIZE {
+            return None;
+        }
+        Some(Report {
+            measurement: bytes[..32].try_into().ok()?,
+            report_data: bytes[32..32 + REPORT_DATA_SIZE].try_into().ok()?,
+            signature: bytes[32 + REPORT_DATA_SIZE..].try_into().ok()?,
+        })
+    }
+}
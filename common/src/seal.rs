@@ -0,0 +1,21 @@
+use sha2::{Digest, Sha256};
+
+use crate::measure::Measurement;
+
+pub const SEAL_LABEL_SIZE: usize = 32;
+pub const SEAL_KEY_SIZE: usize = 32;
+const SEAL_DOMAIN: &[u8] = b"coffer-seal-v1";
+
+/* bound to the device and the initial image, the same enclave gets the same key after reboot */
+pub fn seal_key(
+    device_seed: &[u8; 32],
+    measurement: &Measurement,
+    label: &[u8; SEAL_LABEL_SIZE],
+) -> [u8; SEAL_KEY_SIZE] {
+    let mut hasher = Sha256::new();
+    hasher.update(SEAL_DOMAIN);
+    hasher.update(device_seed);
+    hasher.update(measurement);
+    hasher.update(label);
+    hasher.finalize().into()
+}
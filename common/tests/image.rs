@@ -0,0 +1,87 @@
+/* manifests the loader has to turn down before it carves stacks out of the region */
+use coffer_common::{
+    bundle::{BundleHeader, Manifest, HEADER_SIZE},
+    image::{Image, MAX_THREADS},
+};
+
+const TEXT_VADDR: u64 = 0x1_0000;
+
+fn put(bytes: &mut Vec<u8>, at: usize, value: &[u8]) {
+    if bytes.len() < at + value.len() {
+        bytes.resize(at + value.len(), 0);
+    }
+    bytes[at..at + value.len()].copy_from_slice(value);
+}
+
+/* RISC-V ELF64 with a single text segment */
+fn sample_elf() -> Vec<u8> {
+    let mut elf = Vec::new();
+    put(&mut elf, 0, b"\x7fELF\x02\x01\x01");
+    put(&mut elf, 16, &3u16.to_le_bytes());
+    put(&mut elf, 18, &243u16.to_le_bytes());
+    put(&mut elf, 20, &1u32.to_le_bytes());
+    put(&mut elf, 24, &TEXT_VADDR.to_le_bytes());
+    put(&mut elf, 32, &64u64.to_le_bytes());
+    put(&mut elf, 52, &64u16.to_le_bytes());
+    put(&mut elf, 54, &56u16.to_le_bytes());
+    put(&mut elf, 56, &1u16.to_le_bytes());
+    put(&mut elf, 58, &64u16.to_le_bytes());
+    /* p_type, p_flags, p_offset, p_vaddr, p_paddr, p_filesz, p_memsz, p_align */
+    let fields: [u64; 6] = [0x1000, TEXT_VADDR, TEXT_VADDR, 0x20, 0x20, 0x1000];
+    put(&mut elf, 64, &1u32.to_le_bytes());
+    put(&mut elf, 68, &5u32.to_le_bytes());
+    for (i, field) in fields.iter().enumerate() {
+        put(&mut elf, 72 + i * 8, &field.to_le_bytes());
+    }
+    put(&mut elf, 0x1000, &[0x13; 0x20]);
+    elf
+}
+
+fn bundle(stack_size: u64, threads: u64) -> Vec<u8> {
+    let elf = sample_elf();
+    let header = BundleHeader {
+        manifest: Manifest {
+            memory_size: 0x10_0000,
+            stack_size,
+            entry: TEXT_VADDR,
+            threads,
+            trap_handler: 0,
+            trap_delegated: 0,
+            scrub_exempt: 0,
+        },
+        elf_offset: HEADER_SIZE as u64,
+        elf_size: elf.len() as u64,
+    };
+    let mut bundle = header.to_bytes().to_vec();
+    bundle.extend_from_slice(&elf);
+    bundle
+}
+
+fn error(bundle: &[u8]) -> &'static str {
+    match Image::parse(bundle) {
+        Ok(_) => panic!("image was accepted"),
+        Err(err) => err,
+    }
+}
+
+#[test]
+fn sane_manifest_is_accepted() {
+    assert!(Image::parse(&bundle(0x1000, 2)).is_ok());
+    assert!(Image::parse(&bundle(0x1000, MAX_THREADS)).is_ok());
+}
+
+#[test]
+fn zero_stack_is_rejected() {
+    assert_eq!(error(&bundle(0, 1)), "[ERROR]: threads need a stack");
+}
+
+#[test]
+fn thread_count_is_capped() {
+    assert_eq!(error(&bundle(0x1000, 0)), "[ERROR]: thread count out of range");
+    assert_eq!(
+        error(&bundle(0x1000, MAX_THREADS + 1)),
+        "[ERROR]: thread count out of range"
+    );
+    /* small stacks would otherwise let a huge count slip past the size check */
+    assert_eq!(error(&bundle(0x10, 0x1_0000)), "[ERROR]: thread count out of range");
+}
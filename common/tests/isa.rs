@@ -0,0 +1,69 @@
+/* riscv,isa strings as device trees spell them */
+use coffer_common::isa::{parse_isa, Ext, FeatureSet};
+
+fn letters(features: &FeatureSet) -> String {
+    features.letters().collect()
+}
+
+fn multi_letter(features: &FeatureSet) -> Vec<&str> {
+    features.multi_letter().collect()
+}
+
+#[test]
+fn single_letters_with_supervisor_and_user() {
+    let features = parse_isa("rv64imafdcvsu").unwrap();
+    assert_eq!(letters(&features), "acdfimsuv");
+    assert!(features.has(Ext::V) && features.has(Ext::S) && features.has(Ext::U));
+    assert!(multi_letter(&features).is_empty());
+}
+
+#[test]
+fn versions_on_letters_are_skipped() {
+    let features = parse_isa("rv64i2p1m2p0a2c").unwrap();
+    assert_eq!(letters(&features), "acim");
+    assert!(!features.has(Ext::P));
+}
+
+#[test]
+fn g_expands_to_its_extensions() {
+    let features = parse_isa("rv64gc").unwrap();
+    assert_eq!(letters(&features), "acdfgim");
+    assert!(features.has(Ext::Zicsr) && features.has(Ext::Zifencei));
+}
+
+#[test]
+fn mixed_multi_letter_names() {
+    let features =
+        parse_isa("rv64imac_zicsr_zicbom1p0_sstc_svinval_xtheadcmo_xtheadfoo2p0").unwrap();
+    assert_eq!(letters(&features), "acim");
+    for ext in [Ext::Zicsr, Ext::Zicbom, Ext::Sstc, Ext::Svinval, Ext::XTheadCmo] {
+        assert!(features.has(ext), "{:?}", ext);
+    }
+    assert!(!features.has(Ext::S) && !features.has(Ext::XTheadSync));
+    assert_eq!(
+        multi_letter(&features),
+        ["zicsr", "zicbom", "sstc", "svinval", "xtheadcmo", "xtheadfoo"]
+    );
+}
+
+#[test]
+fn upper_case_and_rv32() {
+    let features = parse_isa("RV32IMA_Zifencei").unwrap();
+    assert_eq!(letters(&features), "aim");
+    assert!(features.has(Ext::Zifencei));
+}
+
+#[test]
+fn malformed_strings_are_rejected() {
+    assert!(parse_isa("rv128i").is_none());
+    assert!(parse_isa("imafdc").is_none());
+    assert!(parse_isa("rv64i-m").is_none());
+}
+
+#[test]
+fn clear_letters_keeps_multi_letter() {
+    let mut features = parse_isa("rv64imac_zicsr").unwrap();
+    features.clear_letters();
+    assert_eq!(letters(&features), "");
+    assert!(features.has(Ext::Zicsr));
+}
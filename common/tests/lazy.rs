@@ -0,0 +1,56 @@
+/* lazy FP/vector switching as runtime.rs drives it around every trap */
+use coffer_common::lazy::{on_entry, save_on_exit, OnEntry, UnitState};
+
+#[test]
+fn kernel_with_fs_off_skips_save_and_load() {
+    /* an ecall from a kernel that has not touched FP since its last context switch */
+    assert_eq!(save_on_exit(UnitState::Off), None);
+    assert_eq!(on_entry(UnitState::Off, true), OnEntry::Keep);
+}
+
+#[test]
+fn off_runtime_disowns_someone_elses_registers() {
+    assert_eq!(on_entry(UnitState::Off, false), OnEntry::Disown);
+}
+
+#[test]
+fn only_dirty_state_is_saved() {
+    assert_eq!(save_on_exit(UnitState::Dirty), Some(UnitState::Clean));
+    assert_eq!(save_on_exit(UnitState::Clean), None);
+    assert_eq!(save_on_exit(UnitState::Initial), None);
+}
+
+#[test]
+fn usable_state_is_loaded_unless_live() {
+    for state in [UnitState::Initial, UnitState::Clean, UnitState::Dirty] {
+        assert_eq!(on_entry(state, false), OnEntry::Load);
+        assert_eq!(on_entry(state, true), OnEntry::Keep);
+    }
+}
+
+#[test]
+fn state_round_trips_through_mstatus_bits() {
+    for bits in 0..4 {
+        assert_eq!(UnitState::from_bits(bits).bits(), bits);
+    }
+    assert_eq!(UnitState::from_bits(0b111), UnitState::Dirty);
+}
+
+/* a kernel trapping over and over without using FP in between */
+#[test]
+fn repeated_traps_save_once() {
+    let (mut saves, mut loads) = (0, 0);
+    let mut kernel = UnitState::Dirty;
+    let mut live = false;
+    for _ in 0..8 {
+        if on_entry(kernel, live) == OnEntry::Load {
+            loads += 1;
+        }
+        live = true;
+        if let Some(state) = save_on_exit(kernel) {
+            saves += 1;
+            kernel = state;
+        }
+    }
+    assert_eq!((saves, loads), (1, 1));
+}
@@ -0,0 +1,128 @@
+/* buddy bookkeeping of the donated memory pool */
+use coffer_common::pool::{MemoryPool, Owner, PAGE_SIZE};
+
+const BASE: usize = 0x8800_0000;
+const SIZE: usize = 16 * PAGE_SIZE;
+
+fn pool() -> MemoryPool {
+    let mut pool = MemoryPool::new();
+    pool.register(BASE, SIZE).unwrap();
+    pool
+}
+
+/* back in one piece: the whole pool can be handed out at once */
+fn assert_merged(pool: &mut MemoryPool) {
+    assert_eq!(pool.alloc(SIZE, Owner::Enclave(9)), Some(BASE));
+    pool.free_all(9);
+}
+
+#[test]
+fn register_rejects_bad_ranges() {
+    let mut pool = pool();
+    assert!(pool.register(BASE + SIZE + 1, PAGE_SIZE).is_err());
+    assert!(pool.register(BASE + SIZE, PAGE_SIZE + 1).is_err());
+    assert!(pool.register(BASE + SIZE, 0).is_err());
+    assert!(pool.register(BASE + PAGE_SIZE, PAGE_SIZE).is_err());
+    assert!(pool.register(usize::MAX & !(PAGE_SIZE - 1), 2 * PAGE_SIZE).is_err());
+}
+
+#[test]
+fn unaligned_registration_is_cut_into_aligned_blocks() {
+    let mut pool = MemoryPool::new();
+    pool.register(BASE + PAGE_SIZE, 3 * PAGE_SIZE).unwrap();
+    assert_eq!(pool.ranges(), [BASE + PAGE_SIZE..BASE + 4 * PAGE_SIZE]);
+    /* 1 + 2 pages, no aligned block of 4 */
+    assert_eq!(pool.alloc(4 * PAGE_SIZE, Owner::Enclave(0)), None);
+    assert_eq!(pool.alloc(2 * PAGE_SIZE, Owner::Enclave(0)), Some(BASE + 2 * PAGE_SIZE));
+    assert_eq!(pool.alloc(PAGE_SIZE, Owner::Enclave(0)), Some(BASE + PAGE_SIZE));
+    assert_eq!(pool.alloc(PAGE_SIZE, Owner::Enclave(0)), None);
+}
+
+#[test]
+fn alloc_splits_the_smallest_fitting_chunk() {
+    let mut pool = pool();
+    assert_eq!(pool.alloc(PAGE_SIZE, Owner::Enclave(1)), Some(BASE));
+    /* halves left behind: 1 page at +1, 2 at +2, 4 at +4, 8 at +8 */
+    assert_eq!(pool.alloc(4 * PAGE_SIZE, Owner::Enclave(2)), Some(BASE + 4 * PAGE_SIZE));
+    assert_eq!(pool.alloc(PAGE_SIZE, Owner::Enclave(2)), Some(BASE + PAGE_SIZE));
+    assert_eq!(pool.owner(BASE), Some(Owner::Enclave(1)));
+    assert_eq!(pool.owner(BASE + 5 * PAGE_SIZE), Some(Owner::Enclave(2)));
+    assert_eq!(pool.owner(BASE + 2 * PAGE_SIZE), Some(Owner::Free));
+    assert_eq!(pool.owner(BASE + SIZE), None);
+    assert_eq!(pool.alloc(16 * PAGE_SIZE, Owner::Enclave(3)), None);
+    /* only powers of two, at least a page */
+    assert_eq!(pool.alloc(3 * PAGE_SIZE, Owner::Enclave(3)), None);
+    assert_eq!(pool.alloc(PAGE_SIZE / 2, Owner::Enclave(3)), None);
+}
+
+#[test]
+fn free_merges_buddies_back() {
+    let mut pool = pool();
+    let a = pool.alloc(PAGE_SIZE, Owner::Enclave(1)).unwrap();
+    let b = pool.alloc(2 * PAGE_SIZE, Owner::Enclave(1)).unwrap();
+    let c = pool.alloc(8 * PAGE_SIZE, Owner::Enclave(2)).unwrap();
+    pool.free(c..c + 8 * PAGE_SIZE, Owner::Enclave(2));
+    pool.free(a..a + PAGE_SIZE, Owner::Enclave(1));
+    pool.free(b..b + 2 * PAGE_SIZE, Owner::Enclave(1));
+    assert_merged(&mut pool);
+}
+
+#[test]
+fn free_only_releases_what_the_owner_holds() {
+    let mut pool = pool();
+    let a = pool.alloc(8 * PAGE_SIZE, Owner::Enclave(1)).unwrap();
+    pool.free(a..a + 8 * PAGE_SIZE, Owner::Enclave(2));
+    assert_eq!(pool.owner(a), Some(Owner::Enclave(1)));
+    pool.free_all(1);
+    assert_merged(&mut pool);
+}
+
+#[test]
+fn partial_free_leaves_no_lasting_fragments() {
+    let mut pool = pool();
+    let a = pool.alloc(8 * PAGE_SIZE, Owner::Enclave(1)).unwrap();
+    /* a page out of the middle, the enclave keeps the rest */
+    let page = a + 3 * PAGE_SIZE;
+    pool.free(page..page + PAGE_SIZE, Owner::Enclave(1));
+    assert_eq!(pool.owner(page), Some(Owner::Free));
+    assert_eq!(pool.owner(page - PAGE_SIZE), Some(Owner::Enclave(1)));
+    assert_eq!(pool.owner(page + PAGE_SIZE), Some(Owner::Enclave(1)));
+    /* the page goes to someone else and comes back */
+    assert_eq!(pool.alloc(PAGE_SIZE, Owner::Enclave(2)), Some(page));
+    pool.free_all(2);
+    pool.free(a..a + 8 * PAGE_SIZE, Owner::Enclave(1));
+    assert_merged(&mut pool);
+}
+
+#[test]
+fn unaligned_free_is_ignored() {
+    let mut pool = pool();
+    let a = pool.alloc(4 * PAGE_SIZE, Owner::Enclave(1)).unwrap();
+    pool.free(a + 8..a + PAGE_SIZE, Owner::Enclave(1));
+    pool.free(a..a + PAGE_SIZE + 1, Owner::Enclave(1));
+    assert_eq!(pool.owner(a), Some(Owner::Enclave(1)));
+    pool.free(a..a + 4 * PAGE_SIZE, Owner::Enclave(1));
+    assert_merged(&mut pool);
+}
+
+#[test]
+fn reclaim_takes_only_free_pages() {
+    let mut pool = pool();
+    let a = pool.alloc(PAGE_SIZE, Owner::Enclave(1)).unwrap();
+    assert!(!pool.reclaim(BASE..BASE + 4 * PAGE_SIZE));
+    assert!(!pool.reclaim(BASE + 4 * PAGE_SIZE..BASE + 4 * PAGE_SIZE + 8));
+    assert_eq!(pool.ranges(), [BASE..BASE + SIZE]);
+
+    assert!(pool.reclaim(BASE + 8 * PAGE_SIZE..BASE + 12 * PAGE_SIZE));
+    assert_eq!(
+        pool.ranges(),
+        [BASE..BASE + 8 * PAGE_SIZE, BASE + 12 * PAGE_SIZE..BASE + SIZE]
+    );
+    assert!(!pool.overlaps(&(BASE + 9 * PAGE_SIZE..BASE + 10 * PAGE_SIZE)));
+    assert_eq!(pool.owner(BASE + 8 * PAGE_SIZE), None);
+    /* what is left still splits and merges */
+    pool.free(a..a + PAGE_SIZE, Owner::Enclave(1));
+    assert_eq!(pool.alloc(8 * PAGE_SIZE, Owner::Enclave(3)), Some(BASE));
+    assert_eq!(pool.alloc(4 * PAGE_SIZE, Owner::Enclave(3)), Some(BASE + 12 * PAGE_SIZE));
+    assert_eq!(pool.alloc(PAGE_SIZE, Owner::Enclave(3)), None);
+}
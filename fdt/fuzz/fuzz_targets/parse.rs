@@ -0,0 +1,44 @@
+#![no_main]
+/* any bytes handed to the parser, then to the builder, must come back as Ok or Err */
+use coffer_fdt::{builder::FdtBuilder, Fdt};
+use libfuzzer_sys::fuzz_target;
+
+const MAX_BLOB: usize = 64 * 1024;
+
+#[repr(C, align(8))]
+struct Aligned([u8; MAX_BLOB]);
+
+/* Fdt borrows 'static memory; the input is copied in, larger ones are cut short */
+static mut BLOB: Aligned = Aligned([0; MAX_BLOB]);
+static mut OUT: Aligned = Aligned([0; MAX_BLOB]);
+
+fuzz_target!(|data: &[u8]| {
+    let len = data.len().min(MAX_BLOB);
+    let blob: &'static [u8] = unsafe {
+        BLOB.0[..len].copy_from_slice(&data[..len]);
+        &BLOB.0[..len]
+    };
+    let fdt = match Fdt::from_bytes(blob) {
+        Ok(fdt) => fdt,
+        Err(_) => return,
+    };
+    fdt.memory_reserve_iter().count();
+    for node in fdt.node_iter() {
+        node.path();
+        node.unit_address();
+        node.compatible().count();
+        node.reg().map(|reg| reg.count());
+        node.parent();
+        node.phandle().map(|phandle| fdt.find_phandle(phandle));
+        for prop in node.props() {
+            prop.name();
+            prop.as_u32();
+            prop.as_strings().count();
+        }
+    }
+    fdt.find_node("serial0");
+    fdt.cpus().count();
+    if let Ok(tree) = FdtBuilder::from_fdt(&fdt) {
+        let _ = tree.serialize(unsafe { &mut OUT.0 });
+    }
+});
@@ -0,0 +1,394 @@
+use alloc::{borrow::Cow, collections::BTreeMap, vec::Vec};
+
+use super::{
+    node::FdtNode,
+    token::{align, FDT_BEGIN_NODE, FDT_END, FDT_END_NODE, FDT_PROP},
+    Fdt,
+};
+
+const FDT_MAGIC: u32 = 0xd00d_feed;
+const FDT_VERSION: u32 = 17;
+const FDT_LAST_COMP_VERSION: u32 = 16;
+const HEADER_SIZE: usize = 40;
+const RESERVE_ENTRY_SIZE: usize = 16;
+/* copying and writing recurse once per level, deeper trees are refused rather than
+ * running the firmware out of stack */
+pub const MAX_DEPTH: usize = 64;
+
+#[derive(Clone)]
+pub struct BuilderProp {
+    pub name: Cow<'static, str>,
+    pub value: Cow<'static, [u8]>,
+}
+
+/* names and values borrow from the source blob until they are edited */
+#[derive(Clone)]
+pub struct BuilderNode {
+    pub name: Cow<'static, str>,
+    props: Vec<BuilderProp>,
+    children: Vec<BuilderNode>,
+}
+
+/* an editable copy of a device tree, serialized into a fresh blob */
+#[derive(Clone)]
+pub struct FdtBuilder {
+    pub boot_cpuid_phys: u32,
+    reservations: Vec<(u64, u64)>,
+    root: BuilderNode,
+}
+
+impl BuilderNode {
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        BuilderNode {
+            name: name.into(),
+            props: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn from_node(node: &FdtNode, depth: usize) -> Result<Self, &'static str> {
+        if depth >= MAX_DEPTH {
+            return Err("[ERROR]: fdt nodes nest too deep");
+        }
+        Ok(BuilderNode {
+            name: Cow::Borrowed(node.name()),
+            props: node
+                .props()
+                .map(|prop| BuilderProp {
+                    name: Cow::Borrowed(prop.name()),
+                    value: Cow::Borrowed(prop.value()),
+                })
+                .collect(),
+            children: node
+                .children()
+                .map(|child| BuilderNode::from_node(&child, depth + 1))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /* same matching as `FdtNode::child`: the full name, or the name without unit address */
+    fn position(&self, name: &str) -> Option<usize> {
+        self.children.iter().position(|child| {
+            child.name == name
+                || (!name.contains('@') && child.name.split('@').next() == Some(name))
+        })
+    }
+
+    pub fn unit_name(&self) -> &str {
+        self.name.split('@').next().unwrap()
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = &BuilderNode> {
+        self.children.iter()
+    }
+
+    pub fn children_mut(&mut self) -> impl Iterator<Item = &mut BuilderNode> {
+        self.children.iter_mut()
+    }
+
+    pub fn child(&self, name: &str) -> Option<&BuilderNode> {
+        self.position(name).map(|idx| &self.children[idx])
+    }
+
+    pub fn child_mut(&mut self, name: &str) -> Option<&mut BuilderNode> {
+        let idx = self.position(name)?;
+        Some(&mut self.children[idx])
+    }
+
+    /* the child called `name`, created empty when missing */
+    pub fn add_child(&mut self, name: impl Into<Cow<'static, str>>) -> &mut BuilderNode {
+        let name = name.into();
+        let idx = match self.children.iter().position(|child| child.name == name) {
+            Some(idx) => idx,
+            None => {
+                self.children.push(BuilderNode::new(name));
+                self.children.len() - 1
+            }
+        };
+        &mut self.children[idx]
+    }
+
+    pub fn remove_child(&mut self, name: &str) -> Option<BuilderNode> {
+        self.position(name).map(|idx| self.children.remove(idx))
+    }
+
+    /* keep the children `keep` says yes to */
+    pub fn retain_children(&mut self, keep: impl FnMut(&BuilderNode) -> bool) {
+        self.children.retain(keep);
+    }
+
+    pub fn props(&self) -> impl Iterator<Item = &BuilderProp> {
+        self.props.iter()
+    }
+
+    pub fn property(&self, name: &str) -> Option<&[u8]> {
+        self.props
+            .iter()
+            .find(|prop| prop.name == name)
+            .map(|prop| &*prop.value)
+    }
+
+    pub fn property_u32(&self, name: &str) -> Option<u32> {
+        let value = self.property(name)?;
+        match value.len() {
+            4 => Some(u32::from_be_bytes([value[0], value[1], value[2], value[3]])),
+            _ => None,
+        }
+    }
+
+    /* the value copied out of the source blob, for edits in place */
+    pub fn property_mut(&mut self, name: &str) -> Option<&mut Vec<u8>> {
+        self.props
+            .iter_mut()
+            .find(|prop| prop.name == name)
+            .map(|prop| prop.value.to_mut())
+    }
+
+    pub fn set_property(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        value: impl Into<Cow<'static, [u8]>>,
+    ) {
+        let name = name.into();
+        let value = value.into();
+        match self.props.iter_mut().find(|prop| prop.name == name) {
+            Some(prop) => prop.value = value,
+            None => self.props.push(BuilderProp { name, value }),
+        }
+    }
+
+    pub fn set_empty(&mut self, name: impl Into<Cow<'static, str>>) {
+        self.set_property(name, Vec::new());
+    }
+
+    pub fn set_u32(&mut self, name: impl Into<Cow<'static, str>>, value: u32) {
+        self.set_property(name, value.to_be_bytes().to_vec());
+    }
+
+    pub fn set_u64(&mut self, name: impl Into<Cow<'static, str>>, value: u64) {
+        self.set_property(name, value.to_be_bytes().to_vec());
+    }
+
+    pub fn set_cells(&mut self, name: impl Into<Cow<'static, str>>, cells: &[u32]) {
+        let value = cells
+            .iter()
+            .flat_map(|cell| cell.to_be_bytes())
+            .collect::<Vec<_>>();
+        self.set_property(name, value);
+    }
+
+    pub fn set_str(&mut self, name: impl Into<Cow<'static, str>>, value: &str) {
+        self.set_strings(name, &[value]);
+    }
+
+    pub fn set_strings(&mut self, name: impl Into<Cow<'static, str>>, values: &[&str]) {
+        let mut value = Vec::new();
+        for s in values {
+            value.extend_from_slice(s.as_bytes());
+            value.push(0);
+        }
+        self.set_property(name, value);
+    }
+
+    pub fn remove_property(&mut self, name: &str) -> bool {
+        let len = self.props.len();
+        self.props.retain(|prop| prop.name != name);
+        self.props.len() != len
+    }
+
+    /* properties of `other` replace ours, children with the same name are merged */
+    pub fn merge(&mut self, other: BuilderNode) {
+        for prop in other.props {
+            self.set_property(prop.name, prop.value);
+        }
+        for child in other.children {
+            self.add_child(child.name.clone()).merge(child);
+        }
+    }
+}
+
+impl FdtBuilder {
+    pub fn from_fdt(fdt: &Fdt) -> Result<Self, &'static str> {
+        let root = fdt.root().ok_or("[ERROR]: fdt has no root node")?;
+        Ok(FdtBuilder {
+            boot_cpuid_phys: fdt.header().boot_cpuid_phys(),
+            reservations: fdt
+                .memory_reserve_iter()
+                .map(|entry| (entry.address() as u64, entry.size() as u64))
+                .collect(),
+            root: BuilderNode::from_node(&root, 0)?,
+        })
+    }
+
+    pub fn root(&self) -> &BuilderNode {
+        &self.root
+    }
+
+    pub fn root_mut(&mut self) -> &mut BuilderNode {
+        &mut self.root
+    }
+
+    pub fn node(&self, path: &str) -> Option<&BuilderNode> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(&self.root, |node, name| node.child(name))
+    }
+
+    pub fn node_mut(&mut self, path: &str) -> Option<&mut BuilderNode> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(&mut self.root, |node, name| node.child_mut(name))
+    }
+
+    /* every missing node along `path` is created */
+    pub fn add_node(&mut self, path: &str) -> &mut BuilderNode {
+        path.split('/').filter(|name| !name.is_empty()).fold(
+            &mut self.root,
+            |node, name| match node.position(name) {
+                Some(idx) => &mut node.children[idx],
+                None => node.add_child(alloc::string::String::from(name)),
+            },
+        )
+    }
+
+    pub fn remove_node(&mut self, path: &str) -> Option<BuilderNode> {
+        let idx = path.rfind('/')?;
+        let (parent, name) = path.split_at(idx);
+        self.node_mut(parent)?.remove_child(&name[1..])
+    }
+
+    pub fn reservations(&self) -> &[(u64, u64)] {
+        &self.reservations
+    }
+
+    pub fn add_reservation(&mut self, address: u64, size: u64) {
+        self.reservations.push((address, size));
+    }
+
+    /* write a version 17 blob into `buf`, which has to be 8 byte aligned; returns its size */
+    pub fn serialize(&self, buf: &mut [u8]) -> Result<usize, &'static str> {
+        if buf.as_ptr() as usize % 8 != 0 {
+            return Err("[ERROR]: fdt buffer is not 8 byte aligned");
+        }
+        /* strings block: every property name once, in first use order */
+        let mut strings = Vec::new();
+        let mut offsets = BTreeMap::new();
+        collect_names(&self.root, &mut strings, &mut offsets, 0)?;
+
+        let off_rsvmap = HEADER_SIZE;
+        let off_struct = off_rsvmap + (self.reservations.len() + 1) * RESERVE_ENTRY_SIZE;
+        let mut writer = Writer {
+            buf,
+            pos: off_struct,
+        };
+        writer.node(&self.root, &offsets, 0)?;
+        writer.put_u32(FDT_END.to_native())?;
+        let off_strings = writer.pos;
+        writer.put(&strings)?;
+        let total_size = writer.pos;
+
+        let buf = writer.buf;
+        for (i, (address, size)) in self
+            .reservations
+            .iter()
+            .chain(core::iter::once(&(0, 0)))
+            .enumerate()
+        {
+            let entry = off_rsvmap + i * RESERVE_ENTRY_SIZE;
+            buf[entry..entry + 8].copy_from_slice(&address.to_be_bytes());
+            buf[entry + 8..entry + 16].copy_from_slice(&size.to_be_bytes());
+        }
+        let header = [
+            FDT_MAGIC,
+            total_size as u32,
+            off_struct as u32,
+            off_strings as u32,
+            off_rsvmap as u32,
+            FDT_VERSION,
+            FDT_LAST_COMP_VERSION,
+            self.boot_cpuid_phys,
+            strings.len() as u32,
+            (off_strings - off_struct) as u32,
+        ];
+        for (i, word) in header.iter().enumerate() {
+            buf[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+        }
+        Ok(total_size)
+    }
+}
+
+fn collect_names<'n>(
+    node: &'n BuilderNode,
+    strings: &mut Vec<u8>,
+    offsets: &mut BTreeMap<&'n str, u32>,
+    depth: usize,
+) -> Result<(), &'static str> {
+    /* edits and merged overlays may nest past what any parsed tree could */
+    if depth >= MAX_DEPTH {
+        return Err("[ERROR]: fdt nodes nest too deep");
+    }
+    for prop in node.props.iter() {
+        if !offsets.contains_key(&*prop.name) {
+            offsets.insert(&*prop.name, strings.len() as u32);
+            strings.extend_from_slice(prop.name.as_bytes());
+            strings.push(0);
+        }
+    }
+    for child in node.children.iter() {
+        collect_names(child, strings, offsets, depth + 1)?;
+    }
+    Ok(())
+}
+
+struct Writer<'b> {
+    buf: &'b mut [u8],
+    pos: usize,
+}
+
+impl<'b> Writer<'b> {
+    fn put(&mut self, bytes: &[u8]) -> Result<(), &'static str> {
+        let end = self.pos + bytes.len();
+        self.buf
+            .get_mut(self.pos..end)
+            .ok_or("[ERROR]: fdt buffer too small")?
+            .copy_from_slice(bytes);
+        self.pos = end;
+        Ok(())
+    }
+
+    fn put_u32(&mut self, value: u32) -> Result<(), &'static str> {
+        self.put(&value.to_be_bytes())
+    }
+
+    /* zero padding up to the next token */
+    fn pad(&mut self) -> Result<(), &'static str> {
+        let padding = align(self.pos, 4) - self.pos;
+        self.put(&[0u8; 4][..padding])
+    }
+
+    fn node(
+        &mut self,
+        node: &BuilderNode,
+        offsets: &BTreeMap<&str, u32>,
+        depth: usize,
+    ) -> Result<(), &'static str> {
+        if depth >= MAX_DEPTH {
+            return Err("[ERROR]: fdt nodes nest too deep");
+        }
+        self.put_u32(FDT_BEGIN_NODE.to_native())?;
+        self.put(node.name.as_bytes())?;
+        self.put(&[0])?;
+        self.pad()?;
+        for prop in node.props.iter() {
+            self.put_u32(FDT_PROP.to_native())?;
+            self.put_u32(prop.value.len() as u32)?;
+            self.put_u32(offsets[&*prop.name])?;
+            self.put(&prop.value)?;
+            self.pad()?;
+        }
+        for child in node.children.iter() {
+            self.node(child, offsets, depth + 1)?;
+        }
+        self.put_u32(FDT_END_NODE.to_native())
+    }
+}
@@ -0,0 +1,106 @@
+use core::mem::size_of;
+
+use endiantype::*;
+const FDT_MAGIC: u32_be = u32_be::from_native(0xd00d_feed);
+/* size_dt_struct, which bounds the structure block, first appeared in version 17 */
+const FDT_VERSION: u32 = 17;
+
+#[repr(C)]
+pub struct FdtHeader {
+    magic: u32_be,
+    total_size: u32_be,
+    off_dt_struct: u32_be,
+    off_dt_strings: u32_be,
+    off_mem_rsvmap: u32_be,
+    version: u32_be,
+    last_comp_version: u32_be,
+    boot_cpuid_phys: u32_be,
+    size_dt_strings: u32_be,
+    size_dt_struct: u32_be,
+}
+
+impl FdtHeader {
+    /* the header at the start of `bytes`, with every block checked to lie within total_size
+     * and total_size within `bytes` */
+    pub fn from_bytes(bytes: &'static [u8]) -> Result<&'static FdtHeader, &'static str> {
+        if bytes.len() < size_of::<FdtHeader>() {
+            return Err("[ERROR]: fdt is shorter than its header");
+        }
+        if bytes.as_ptr() as usize % 4 != 0 {
+            return Err("[ERROR]: fdt is not 4 byte aligned");
+        }
+        let header = unsafe { &*(bytes.as_ptr() as *const FdtHeader) };
+        if header.magic != FDT_MAGIC {
+            return Err("[ERROR]: FDT_MAGIC is not 0xd00dfeed");
+        }
+        if header.version.to_native() < FDT_VERSION
+            || header.last_comp_version.to_native() > FDT_VERSION
+        {
+            return Err("[ERROR]: fdt version is not supported");
+        }
+        if header.total_size() > bytes.len() {
+            return Err("[ERROR]: fdt total_size is past the buffer");
+        }
+        header.check()?;
+        Ok(header)
+    }
+
+    pub fn as_ptr(&self) -> *const u8 {
+        self as *const FdtHeader as *const u8
+    }
+
+    pub fn total_size(&self) -> usize {
+        self.total_size.to_native() as usize
+    }
+
+    /* offset of the memory reservation block within the blob */
+    pub fn memory_reserve_offset(&self) -> usize {
+        self.off_mem_rsvmap.to_native() as usize
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version.to_native()
+    }
+
+    pub fn boot_cpuid_phys(&self) -> u32 {
+        self.boot_cpuid_phys.to_native()
+    }
+
+    /* offset and length of the structure block within the blob */
+    pub fn struct_range(&self) -> (usize, usize) {
+        (
+            self.off_dt_struct.to_native() as usize,
+            self.size_dt_struct.to_native() as usize,
+        )
+    }
+
+    /* offset and length of the strings block within the blob */
+    pub fn strings_range(&self) -> (usize, usize) {
+        (
+            self.off_dt_strings.to_native() as usize,
+            self.size_dt_strings.to_native() as usize,
+        )
+    }
+
+    fn check(&self) -> Result<(), &'static str> {
+        let total_size = self.total_size();
+        let within = |(offset, size): (usize, usize)| {
+            matches!(offset.checked_add(size), Some(end) if end <= total_size)
+        };
+        if total_size < size_of::<FdtHeader>() {
+            return Err("[ERROR]: fdt total_size is smaller than its header");
+        }
+        if !within(self.strings_range()) {
+            return Err("[ERROR]: fdt dt_strings overflowed");
+        }
+        let (struct_offset, _) = self.struct_range();
+        if !within(self.struct_range()) || struct_offset % 4 != 0 {
+            return Err("[ERROR]: fdt dt_struct overflowed");
+        }
+        let rsvmap = self.memory_reserve_offset();
+        if rsvmap % 8 != 0 || rsvmap < size_of::<FdtHeader>() || rsvmap > total_size {
+            return Err("[ERROR]: fdt mem_rsvmap is misplaced");
+        }
+        Ok(())
+    }
+}
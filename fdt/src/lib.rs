@@ -0,0 +1,140 @@
+#![no_std]
+
+extern crate alloc;
+
+use core::slice;
+
+use self::{
+    header::FdtHeader,
+    memory_reserve::{FdtMemoryReserveEntry, FdtMemoryReserveIter},
+    node::{FdtNode, FdtNodeIter},
+};
+
+pub mod builder;
+pub mod header;
+pub mod memory_reserve;
+pub mod node;
+pub mod overlay;
+pub mod prop;
+mod token;
+
+pub struct Fdt {
+    header: &'static FdtHeader,
+    inner_buffer: &'static [u8],
+}
+
+impl Fdt {
+    /* a blob somewhere in memory, its total_size is trusted once the magic matches */
+    pub unsafe fn from_ptr(fdt_ptr: *const u8) -> Result<Self, &'static str> {
+        if fdt_ptr.is_null() {
+            return Err("[ERROR]: fdt pointer is null");
+        }
+        if fdt_ptr as usize % 4 != 0 {
+            return Err("[ERROR]: fdt is not 4 byte aligned");
+        }
+        let head = slice::from_raw_parts(fdt_ptr, 8);
+        if head[..4] != 0xd00d_feed_u32.to_be_bytes() {
+            return Err("[ERROR]: FDT_MAGIC is not 0xd00dfeed");
+        }
+        let total_size = u32::from_be_bytes([head[4], head[5], head[6], head[7]]) as usize;
+        Fdt::from_bytes(slice::from_raw_parts(fdt_ptr, total_size))
+    }
+
+    /* nothing past `bytes`, or past total_size within it, is ever read */
+    pub fn from_bytes(bytes: &'static [u8]) -> Result<Self, &'static str> {
+        let header = FdtHeader::from_bytes(bytes)?;
+        Ok(Fdt {
+            header,
+            inner_buffer: &bytes[..header.total_size()],
+        })
+    }
+
+    pub fn header(&self) -> &FdtHeader {
+        self.header
+    }
+
+    pub fn as_bytes(&self) -> &'static [u8] {
+        self.inner_buffer
+    }
+
+    pub(crate) fn struct_block(&self) -> &'static [u8] {
+        let (offset, size) = self.header.struct_range();
+        &self.inner_buffer[offset..offset + size]
+    }
+
+    pub(crate) fn strings_block(&self) -> &'static [u8] {
+        let (offset, size) = self.header.strings_range();
+        &self.inner_buffer[offset..offset + size]
+    }
+
+    /* the NUL terminated string at `offset` of the strings block, which it may not leave */
+    pub fn str_at_offset(&self, offset: usize) -> Option<&'static str> {
+        let tail = self.strings_block().get(offset..)?;
+        let len = tail.iter().position(|b| *b == 0)?;
+        core::str::from_utf8(&tail[..len]).ok()
+    }
+
+    pub fn memory_reserve_iter(&self) -> impl Iterator<Item = FdtMemoryReserveEntry> {
+        FdtMemoryReserveIter::new(self.inner_buffer, self.header.memory_reserve_offset())
+    }
+
+    pub fn node_iter(&self) -> FdtNodeIter<'_> {
+        FdtNodeIter::new(self)
+    }
+
+    pub fn root(&self) -> Option<FdtNode<'_>> {
+        self.node_iter().next()
+    }
+
+    /* "/cpus/cpu@0", "/cpus/cpu" when the unit address is unambiguous, or an alias; aliases
+     * must hold a full path, they are never resolved through one another */
+    pub fn find_node(&self, path: &str) -> Option<FdtNode<'_>> {
+        let path = match path.starts_with('/') {
+            true => path,
+            false => {
+                let (alias, rest) = match path.find('/') {
+                    Some(idx) => path.split_at(idx),
+                    None => (path, ""),
+                };
+                let target = self.find_node("/aliases")?.property(alias)?.as_str()?;
+                if !target.starts_with('/') {
+                    return None;
+                }
+                return self.root()?.find_relative(target)?.find_relative(rest);
+            }
+        };
+        self.root()?.find_relative(path)
+    }
+
+    pub fn find_phandle(&self, phandle: u32) -> Option<FdtNode<'_>> {
+        self.node_iter()
+            .find(|node| node.phandle() == Some(phandle))
+    }
+
+    pub fn find_compatible(&self, with: &[&str]) -> Option<FdtNode<'_>> {
+        self.node_iter().find(|node| node.is_compatible(with))
+    }
+
+    pub fn find_all_compatible<'a>(
+        &'a self,
+        with: &'a [&'a str],
+    ) -> impl Iterator<Item = FdtNode<'a>> {
+        self.node_iter()
+            .filter(move |node| node.is_compatible(with))
+    }
+
+    pub fn cpus(&self) -> impl Iterator<Item = FdtNode<'_>> {
+        self.find_node("/cpus")
+            .into_iter()
+            .flat_map(|cpus| cpus.children())
+            .filter(|node| node.unit_name() == "cpu")
+    }
+}
+
+impl<'a> FdtNode<'a> {
+    fn find_relative(self, path: &str) -> Option<FdtNode<'a>> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |node, name| node.child(name))
+    }
+}
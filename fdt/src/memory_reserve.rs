@@ -0,0 +1,61 @@
+use core::convert::TryInto;
+
+/* one /memreserve/ entry, decoded out of the blob */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FdtMemoryReserveEntry {
+    address: u64,
+    size: u64,
+}
+
+impl FdtMemoryReserveEntry {
+    pub fn valid(&self) -> bool {
+        !(self.address == 0 && self.size == 0)
+    }
+
+    pub fn address(&self) -> usize {
+        self.address as usize
+    }
+
+    pub fn size(&self) -> usize {
+        self.size as usize
+    }
+
+    /* entries are decoded copies now, setting one leaves the blob alone */
+    pub fn set_address(&mut self, new_addr: u64) {
+        self.address = new_addr;
+    }
+
+    pub fn set_size(&mut self, new_size: u64) {
+        self.size = new_size;
+    }
+}
+
+/* entries up to the terminating zero one, or the end of the blob if that is missing */
+pub(crate) struct FdtMemoryReserveIter {
+    block: &'static [u8],
+    offset: usize,
+}
+
+impl Iterator for FdtMemoryReserveIter {
+    type Item = FdtMemoryReserveEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = self.block.get(self.offset..self.offset.checked_add(16)?)?;
+        let entry = FdtMemoryReserveEntry {
+            address: u64::from_be_bytes(bytes[..8].try_into().unwrap()),
+            size: u64::from_be_bytes(bytes[8..].try_into().unwrap()),
+        };
+        if entry.valid() {
+            self.offset += 16;
+            Some(entry)
+        } else {
+            None
+        }
+    }
+}
+
+impl FdtMemoryReserveIter {
+    pub fn new(block: &'static [u8], offset: usize) -> Self {
+        FdtMemoryReserveIter { block, offset }
+    }
+}
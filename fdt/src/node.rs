@@ -0,0 +1,305 @@
+use alloc::{string::String, vec::Vec};
+
+use super::{
+    prop::FdtProp,
+    token::{next_token, Token},
+    Fdt,
+};
+
+/* #address-cells and #size-cells when a node does not say */
+const DEFAULT_ADDRESS_CELLS: u32 = 2;
+const DEFAULT_SIZE_CELLS: u32 = 1;
+
+/* a node, found by its FDT_BEGIN_NODE offset in the structure block */
+#[derive(Clone, Copy)]
+pub struct FdtNode<'a> {
+    pub(crate) fdt: &'a Fdt,
+    pub(crate) offset: usize,
+    name: &'static str,
+    /* first token after the name */
+    body: usize,
+}
+
+/* one (address, size) pair of a reg property */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FdtReg {
+    pub address: u64,
+    pub size: Option<u64>,
+}
+
+impl<'a> FdtNode<'a> {
+    pub(crate) fn at(fdt: &'a Fdt, offset: usize) -> Option<Self> {
+        match next_token(fdt.struct_block(), offset)? {
+            (Token::BeginNode(name), body) => Some(FdtNode {
+                fdt,
+                offset,
+                name,
+                body,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn fdt(&self) -> &'a Fdt {
+        self.fdt
+    }
+
+    /* full name, e.g. "cpu@0", the root is "" */
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /* name without the unit address */
+    pub fn unit_name(&self) -> &'static str {
+        self.name.split('@').next().unwrap()
+    }
+
+    pub fn unit_address(&self) -> Option<&'static str> {
+        self.name.split_once('@').map(|(_, address)| address)
+    }
+
+    pub fn props(&self) -> FdtPropIter<'a> {
+        FdtPropIter {
+            fdt: self.fdt,
+            offset: self.body,
+        }
+    }
+
+    pub fn property(&self, name: &str) -> Option<FdtProp<'a>> {
+        self.props().find(|prop| prop.name() == name)
+    }
+
+    pub fn children(&self) -> FdtChildIter<'a> {
+        FdtChildIter {
+            fdt: self.fdt,
+            offset: Some(self.body),
+        }
+    }
+
+    pub fn child(&self, name: &str) -> Option<FdtNode<'a>> {
+        self.children().find(|child| {
+            child.name() == name || (!name.contains('@') && child.unit_name() == name)
+        })
+    }
+
+    /* the structure block only links downwards, walk from the root */
+    pub fn parent(&self) -> Option<FdtNode<'a>> {
+        self.ancestors().pop()
+    }
+
+    /* root first, excluding the node itself */
+    fn ancestors(&self) -> Vec<FdtNode<'a>> {
+        let mut stack = Vec::new();
+        let mut offset = 0;
+        let block = self.fdt.struct_block();
+        while let Some((token, next)) = next_token(block, offset) {
+            match token {
+                Token::BeginNode(_) if offset == self.offset => return stack,
+                Token::BeginNode(_) => stack.extend(FdtNode::at(self.fdt, offset)),
+                Token::EndNode => {
+                    stack.pop();
+                }
+                Token::End => break,
+                _ => {}
+            }
+            offset = next;
+        }
+        Vec::new()
+    }
+
+    pub fn path(&self) -> String {
+        let mut path = String::new();
+        for node in self.ancestors().iter().skip(1) {
+            path.push('/');
+            path.push_str(node.name());
+        }
+        path.push('/');
+        path.push_str(self.name);
+        path
+    }
+
+    pub fn compatible(&self) -> impl Iterator<Item = &'static str> + 'a {
+        self.property("compatible")
+            .into_iter()
+            .flat_map(|prop| prop.as_strings())
+    }
+
+    pub fn is_compatible(&self, with: &[&str]) -> bool {
+        self.compatible().any(|c| with.contains(&c))
+    }
+
+    pub fn phandle(&self) -> Option<u32> {
+        self.property("phandle")
+            .or_else(|| self.property("linux,phandle"))
+            .and_then(|prop| prop.as_u32())
+    }
+
+    /* cells of the reg entries of this node's children */
+    pub fn address_cells(&self) -> u32 {
+        self.property("#address-cells")
+            .and_then(|prop| prop.as_u32())
+            .unwrap_or(DEFAULT_ADDRESS_CELLS)
+    }
+
+    pub fn size_cells(&self) -> u32 {
+        self.property("#size-cells")
+            .and_then(|prop| prop.as_u32())
+            .unwrap_or(DEFAULT_SIZE_CELLS)
+    }
+
+    /* reg decoded with the parent's #address-cells and #size-cells */
+    pub fn reg(&self) -> Option<impl Iterator<Item = FdtReg>> {
+        let prop = self.property("reg")?;
+        let (address_cells, size_cells) = match self.parent() {
+            Some(parent) => (parent.address_cells(), parent.size_cells()),
+            None => (DEFAULT_ADDRESS_CELLS, DEFAULT_SIZE_CELLS),
+        };
+        let (address_cells, size_cells) = (address_cells as usize, size_cells as usize);
+        if address_cells == 0 || address_cells > 2 || size_cells > 2 {
+            return None;
+        }
+        let cells: Vec<u32> = prop.as_u32_list().collect();
+        let regs: Vec<FdtReg> = cells
+            .chunks_exact(address_cells + size_cells)
+            .map(|entry| {
+                let (address, size) = entry.split_at(address_cells);
+                FdtReg {
+                    address: cells_to_u64(address),
+                    size: match size_cells {
+                        0 => None,
+                        _ => Some(cells_to_u64(size)),
+                    },
+                }
+            })
+            .collect();
+        Some(regs.into_iter())
+    }
+}
+
+fn cells_to_u64(cells: &[u32]) -> u64 {
+    cells.iter().fold(0, |acc, cell| (acc << 32) | *cell as u64)
+}
+
+pub struct FdtPropIter<'a> {
+    fdt: &'a Fdt,
+    offset: usize,
+}
+
+impl<'a> Iterator for FdtPropIter<'a> {
+    type Item = FdtProp<'a>;
+
+    /* properties come before the first child */
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (token, next) = next_token(self.fdt.struct_block(), self.offset)?;
+            match token {
+                Token::Nop => self.offset = next,
+                Token::Prop(nameoff, value) => {
+                    self.offset = next;
+                    return Some(FdtProp {
+                        fdt: self.fdt,
+                        nameoff,
+                        value,
+                    });
+                }
+                _ => return None,
+            }
+        }
+    }
+}
+
+pub struct FdtChildIter<'a> {
+    fdt: &'a Fdt,
+    /* `None` once the parent's FDT_END_NODE was reached */
+    offset: Option<usize>,
+}
+
+impl<'a> Iterator for FdtChildIter<'a> {
+    type Item = FdtNode<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let block = self.fdt.struct_block();
+        loop {
+            let offset = self.offset?;
+            let (token, next) = next_token(block, offset)?;
+            match token {
+                Token::Nop | Token::Prop(..) => self.offset = Some(next),
+                Token::BeginNode(_) => {
+                    let child = FdtNode::at(self.fdt, offset)?;
+                    self.offset = child.end();
+                    return Some(child);
+                }
+                Token::EndNode | Token::End => {
+                    self.offset = None;
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl<'a> FdtNode<'a> {
+    /* offset right after this node's FDT_END_NODE */
+    pub(crate) fn end(&self) -> Option<usize> {
+        let block = self.fdt.struct_block();
+        let mut depth = 0usize;
+        let mut offset = self.offset;
+        loop {
+            let (token, next) = next_token(block, offset)?;
+            match token {
+                Token::BeginNode(_) => depth += 1,
+                Token::EndNode => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(next);
+                    }
+                }
+                Token::End => return None,
+                _ => {}
+            }
+            offset = next;
+        }
+    }
+}
+
+/* every node in document order, the root first */
+pub struct FdtNodeIter<'a> {
+    fdt: &'a Fdt,
+    offset: Option<usize>,
+}
+
+impl<'a> FdtNodeIter<'a> {
+    pub(crate) fn new(fdt: &'a Fdt) -> Self {
+        FdtNodeIter {
+            fdt,
+            offset: Some(0),
+        }
+    }
+}
+
+impl<'a> Iterator for FdtNodeIter<'a> {
+    type Item = FdtNode<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let block = self.fdt.struct_block();
+        loop {
+            let offset = self.offset?;
+            let (token, next) = match next_token(block, offset) {
+                Some(token) => token,
+                None => {
+                    self.offset = None;
+                    return None;
+                }
+            };
+            self.offset = Some(next);
+            match token {
+                Token::BeginNode(_) => return FdtNode::at(self.fdt, offset),
+                Token::End => {
+                    self.offset = None;
+                    return None;
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use alloc::{format, string::String, vec::Vec};
+
+use super::{
+    builder::{BuilderNode, FdtBuilder},
+    Fdt,
+};
+
+/* the phandle a node carries, under either name */
+fn phandle_of(node: &BuilderNode) -> Option<u32> {
+    node.property_u32("phandle")
+        .or_else(|| node.property_u32("linux,phandle"))
+}
+
+fn max_phandle(node: &BuilderNode) -> u32 {
+    let own = phandle_of(node).unwrap_or(0);
+    node.children().map(max_phandle).fold(own, u32::max)
+}
+
+/* path of the node with `phandle` below `node`, which sits at `path` */
+fn phandle_path(node: &BuilderNode, phandle: u32, path: &str) -> Option<String> {
+    if phandle_of(node) == Some(phandle) {
+        return Some(String::from(path));
+    }
+    node.children().find_map(|child| {
+        let child_path = match path {
+            "/" => format!("/{}", child.name),
+            _ => format!("{}/{}", path, child.name),
+        };
+        phandle_path(child, phandle, &child_path)
+    })
+}
+
+/* NUL separated strings of a property value */
+fn strings(value: &[u8]) -> impl Iterator<Item = &str> {
+    value
+        .split(|b| *b == 0)
+        .filter(|s| !s.is_empty())
+        .filter_map(|s| core::str::from_utf8(s).ok())
+}
+
+/* the big-endian cell at byte `offset` of a property */
+fn cell_mut<'n>(
+    node: &'n mut BuilderNode,
+    name: &str,
+    offset: usize,
+) -> Result<&'n mut [u8], &'static str> {
+    let value = node
+        .property_mut(name)
+        .ok_or("[ERROR]: overlay fixup names a missing property")?;
+    match offset.checked_add(4) {
+        Some(end) if end <= value.len() => Ok(&mut value[offset..end]),
+        _ => Err("[ERROR]: overlay fixup points past its property"),
+    }
+}
+
+/* the node a __symbols__ label names in the base tree, given a phandle if it has none */
+fn label_phandle(tree: &mut FdtBuilder, label: &str) -> Result<u32, &'static str> {
+    let path = tree
+        .node("/__symbols__")
+        .and_then(|symbols| symbols.property(label))
+        .and_then(|value| strings(value).next())
+        .map(String::from)
+        .ok_or("[ERROR]: overlay label not in the base __symbols__")?;
+    let next = max_phandle(tree.root()) + 1;
+    let node = tree
+        .node_mut(&path)
+        .ok_or("[ERROR]: __symbols__ names a missing node")?;
+    match phandle_of(node) {
+        Some(phandle) => Ok(phandle),
+        None => {
+            node.set_u32("phandle", next);
+            Ok(next)
+        }
+    }
+}
+
+/* phandles defined by the overlay move above the base tree's */
+fn shift_phandles(node: &mut BuilderNode, delta: u32) -> Result<(), &'static str> {
+    for name in ["phandle", "linux,phandle"] {
+        if let Some(phandle) = node.property_u32(name) {
+            let phandle = phandle
+                .checked_add(delta)
+                .ok_or("[ERROR]: overlay phandles overflow")?;
+            node.set_u32(name, phandle);
+        }
+    }
+    for child in node.children_mut() {
+        shift_phandles(child, delta)?;
+    }
+    Ok(())
+}
+
+/* __local_fixups__ mirrors the overlay, each property lists the offsets of its references */
+fn local_fixups(
+    node: &mut BuilderNode,
+    fixups: &BuilderNode,
+    delta: u32,
+) -> Result<(), &'static str> {
+    for fixup in fixups.props() {
+        for offset in fixup.value.chunks_exact(4) {
+            let offset = u32::from_be_bytes([offset[0], offset[1], offset[2], offset[3]]);
+            let cell = cell_mut(node, &fixup.name, offset as usize)?;
+            let phandle = u32::from_be_bytes([cell[0], cell[1], cell[2], cell[3]]);
+            let phandle = phandle
+                .checked_add(delta)
+                .ok_or("[ERROR]: overlay phandles overflow")?;
+            cell.copy_from_slice(&phandle.to_be_bytes());
+        }
+    }
+    for child_fixups in fixups.children() {
+        let child = node
+            .child_mut(&child_fixups.name)
+            .ok_or("[ERROR]: __local_fixups__ names a missing node")?;
+        local_fixups(child, child_fixups, delta)?;
+    }
+    Ok(())
+}
+
+/* the base tree path a fragment applies to, from `target` or `target-path` */
+fn fragment_target(tree: &FdtBuilder, fragment: &BuilderNode) -> Result<String, &'static str> {
+    if let Some(phandle) = fragment.property_u32("target") {
+        return phandle_path(tree.root(), phandle, "/")
+            .ok_or("[ERROR]: overlay target phandle not in the base tree");
+    }
+    let path = fragment
+        .property("target-path")
+        .and_then(|value| strings(value).next())
+        .ok_or("[ERROR]: overlay fragment without target")?;
+    if path.starts_with('/') {
+        return Ok(String::from(path));
+    }
+    tree.node("/aliases")
+        .and_then(|aliases| aliases.property(path))
+        .and_then(|value| strings(value).next())
+        .map(String::from)
+        .ok_or("[ERROR]: overlay target alias not in the base tree")
+}
+
+/* apply one overlay blob to `tree`, which is left untouched on error */
+pub fn apply_overlay(tree: &mut FdtBuilder, overlay: &Fdt) -> Result<(), &'static str> {
+    let mut patched = tree.clone();
+    let mut overlay = FdtBuilder::from_fdt(overlay)?;
+    let root = overlay.root_mut();
+    let fixups = root.remove_child("__fixups__");
+    let local = root.remove_child("__local_fixups__");
+    let symbols = root.remove_child("__symbols__");
+
+    /* labels are resolved first, the base tree may need new phandles for them */
+    let mut external = Vec::new();
+    for fixup in fixups.iter().flat_map(|fixups| fixups.props()) {
+        let phandle = label_phandle(&mut patched, &fixup.name)?;
+        for location in strings(&fixup.value) {
+            let mut parts = location.rsplitn(3, ':');
+            let (offset, name, path) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(offset), Some(name), Some(path)) => (offset, name, path),
+                _ => return Err("[ERROR]: malformed overlay fixup"),
+            };
+            let offset = offset
+                .parse::<usize>()
+                .map_err(|_| "[ERROR]: malformed overlay fixup")?;
+            external.push((String::from(path), String::from(name), offset, phandle));
+        }
+    }
+
+    let delta = max_phandle(patched.root());
+    shift_phandles(overlay.root_mut(), delta)?;
+    if let Some(local) = &local {
+        local_fixups(overlay.root_mut(), local, delta)?;
+    }
+    for (path, name, offset, phandle) in external {
+        let node = overlay
+            .node_mut(&path)
+            .ok_or("[ERROR]: overlay fixup names a missing node")?;
+        cell_mut(node, &name, offset)?.copy_from_slice(&phandle.to_be_bytes());
+    }
+
+    /* fragments are merged into their targets, labels follow them into __symbols__ */
+    let mut targets = Vec::new();
+    let fragments = overlay
+        .root()
+        .children()
+        .filter(|fragment| fragment.child("__overlay__").is_some())
+        .map(|fragment| {
+            fragment_target(&patched, fragment).map(|target| (fragment.name.clone(), target))
+        })
+        .collect::<Result<Vec<_>, &'static str>>()?;
+    for (name, target) in fragments {
+        let content = overlay
+            .node_mut(&name)
+            .and_then(|fragment| fragment.remove_child("__overlay__"))
+            .unwrap();
+        patched
+            .node_mut(&target)
+            .ok_or("[ERROR]: overlay target not in the base tree")?
+            .merge(content);
+        targets.push((format!("/{}/__overlay__", name), target));
+    }
+    for symbol in symbols.iter().flat_map(|symbols| symbols.props()) {
+        let path = match strings(&symbol.value).next() {
+            Some(path) => path,
+            None => continue,
+        };
+        let moved = targets.iter().find_map(|(from, to)| {
+            let rest = path.strip_prefix(from.as_str())?;
+            match (to.as_str(), rest) {
+                (to, "") => Some(String::from(to)),
+                (_, rest) if !rest.starts_with('/') => None,
+                ("/", rest) => Some(String::from(rest)),
+                (to, rest) => Some(format!("{}{}", to, rest)),
+            }
+        });
+        if let Some(moved) = moved {
+            patched
+                .add_node("/__symbols__")
+                .set_str(symbol.name.clone(), &moved);
+        }
+    }
+    *tree = patched;
+    Ok(())
+}
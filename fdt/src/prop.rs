@@ -0,0 +1,77 @@
+use core::convert::TryInto;
+
+use super::Fdt;
+
+/* a property and its raw big-endian value */
+#[derive(Clone, Copy)]
+pub struct FdtProp<'a> {
+    pub(crate) fdt: &'a Fdt,
+    pub(crate) nameoff: usize,
+    pub(crate) value: &'static [u8],
+}
+
+impl<'a> FdtProp<'a> {
+    pub fn name(&self) -> &'static str {
+        self.fdt.str_at_offset(self.nameoff).unwrap_or("")
+    }
+
+    pub fn name_offset(&self) -> usize {
+        self.nameoff
+    }
+
+    pub fn value(&self) -> &'static [u8] {
+        self.value
+    }
+
+    pub fn len(&self) -> usize {
+        self.value.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.value.try_into().ok()?))
+    }
+
+    /* one or two cells, e.g. clock-frequency */
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.value.len() {
+            4 => self.as_u32().map(u64::from),
+            8 => Some(u64::from_be_bytes(self.value.try_into().ok()?)),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_u64().map(|v| v as usize)
+    }
+
+    pub fn as_u32_list(&self) -> impl Iterator<Item = u32> + 'static {
+        self.value
+            .chunks_exact(4)
+            .map(|cell| u32::from_be_bytes(cell.try_into().unwrap()))
+    }
+
+    /* a single NUL terminated string */
+    pub fn as_str(&self) -> Option<&'static str> {
+        let (last, bytes) = self.value.split_last()?;
+        if *last != 0 {
+            return None;
+        }
+        core::str::from_utf8(bytes).ok()
+    }
+
+    /* NUL separated strings, e.g. compatible */
+    pub fn as_strings(&self) -> impl Iterator<Item = &'static str> + 'static {
+        let value = match self.value.split_last() {
+            Some((0, bytes)) => bytes,
+            _ => &[],
+        };
+        value
+            .split(|b| *b == 0)
+            .filter(move |_| !value.is_empty())
+            .filter_map(|s| core::str::from_utf8(s).ok())
+    }
+}
@@ -0,0 +1,53 @@
+use core::convert::TryInto;
+
+use endiantype::*;
+pub const FDT_BEGIN_NODE: u32_be = u32_be::from_native(0x0000_0001);
+pub const FDT_END_NODE: u32_be = u32_be::from_native(0x0000_0002);
+pub const FDT_PROP: u32_be = u32_be::from_native(0x0000_0003);
+pub const FDT_NOP: u32_be = u32_be::from_native(0x0000_0004);
+pub const FDT_END: u32_be = u32_be::from_native(0x0000_0009);
+
+/* one decoded token of the structure block */
+pub(crate) enum Token {
+    BeginNode(&'static str),
+    EndNode,
+    /* name offset into the strings block, value */
+    Prop(usize, &'static [u8]),
+    Nop,
+    End,
+}
+
+/* big-endian word at `offset`, `None` past the end of `block` */
+pub(crate) fn read_u32(block: &[u8], offset: usize) -> Option<u32> {
+    let bytes = block.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+/* the token at `offset` of the structure block and the offset of the one after it */
+pub(crate) fn next_token(block: &'static [u8], offset: usize) -> Option<(Token, usize)> {
+    let token = u32_be::from_native(read_u32(block, offset)?);
+    let body = offset + 4;
+    if token == FDT_BEGIN_NODE {
+        let len = block.get(body..)?.iter().position(|b| *b == 0)?;
+        let name = core::str::from_utf8(&block[body..body + len]).ok()?;
+        Some((Token::BeginNode(name), align(body + len + 1, 4)))
+    } else if token == FDT_PROP {
+        let len = read_u32(block, body)? as usize;
+        let nameoff = read_u32(block, body + 4)? as usize;
+        let value = block.get(body + 8..(body + 8).checked_add(len)?)?;
+        Some((Token::Prop(nameoff, value), align(body + 8 + len, 4)))
+    } else if token == FDT_END_NODE {
+        Some((Token::EndNode, body))
+    } else if token == FDT_NOP {
+        Some((Token::Nop, body))
+    } else if token == FDT_END {
+        Some((Token::End, body))
+    } else {
+        None
+    }
+}
+
+#[inline]
+pub(crate) fn align(addr: usize, alignment: usize) -> usize {
+    (addr + (alignment - 1)) & !(alignment - 1)
+}
@@ -0,0 +1,362 @@
+/* the shipped D1 tree, checked against its source in dtb/sunxi.dts */
+use coffer_fdt::{builder::FdtBuilder, node::FdtReg, overlay::apply_overlay, Fdt};
+
+static SUNXI_DTB: &[u8] = include_bytes!("../../dtb/sunxi.dtb");
+static SUNXI_DTS: &str = include_str!("../../dtb/sunxi.dts");
+
+/* blobs need 8 byte alignment and a 'static lifetime, tests can afford to leak */
+fn leak_aligned(bytes: &[u8]) -> &'static mut [u8] {
+    let words = vec![0u64; (bytes.len() + 7) / 8].leak();
+    let buf = unsafe { std::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, bytes.len()) };
+    buf.copy_from_slice(bytes);
+    buf
+}
+
+fn sunxi() -> Fdt {
+    Fdt::from_bytes(leak_aligned(SUNXI_DTB)).unwrap()
+}
+
+/* (path, property count) of every node in the dts, in document order */
+fn dts_nodes() -> Vec<(String, usize)> {
+    let mut stack: Vec<String> = Vec::new();
+    let mut nodes: Vec<(String, usize)> = Vec::new();
+    let mut open: Vec<usize> = Vec::new();
+    for line in SUNXI_DTS.lines().map(str::trim) {
+        if let Some(name) = line.strip_suffix(" {") {
+            let path = match (stack.last(), name) {
+                (None, _) => String::from("/"),
+                (Some(parent), name) if parent == "/" => format!("/{}", name),
+                (Some(parent), name) => format!("{}/{}", parent, name),
+            };
+            stack.push(path.clone());
+            open.push(nodes.len());
+            nodes.push((path, 0));
+        } else if line == "};" {
+            stack.pop();
+            open.pop();
+        } else if line.ends_with(';') && !line.starts_with('/') {
+            nodes[*open.last().unwrap()].1 += 1;
+        }
+    }
+    nodes
+}
+
+#[test]
+fn every_node_and_property_of_the_dts() {
+    let fdt = sunxi();
+    let parsed: Vec<(String, usize)> = fdt
+        .node_iter()
+        .map(|node| (node.path(), node.props().count()))
+        .collect();
+    assert_eq!(parsed, dts_nodes());
+}
+
+#[test]
+fn memory_reservations() {
+    let fdt = sunxi();
+    let reserved: Vec<(usize, usize)> = fdt
+        .memory_reserve_iter()
+        .map(|entry| (entry.address(), entry.size()))
+        .collect();
+    assert_eq!(reserved, [(0x4000_0000, 0x20_0000), (0x4200_0000, 0x10_0000)]);
+}
+
+#[test]
+fn root_properties() {
+    let fdt = sunxi();
+    let root = fdt.root().unwrap();
+    assert_eq!(root.property("model").unwrap().as_str(), Some("sun20iw1p1"));
+    assert_eq!(
+        root.compatible().collect::<Vec<_>>(),
+        ["allwinner,d1", "arm,sun20iw1p1", "allwinner,sun20iw1p1"]
+    );
+    assert_eq!((root.address_cells(), root.size_cells()), (2, 2));
+    assert!(root.parent().is_none());
+}
+
+#[test]
+fn cpus() {
+    let fdt = sunxi();
+    let cpus: Vec<_> = fdt.cpus().collect();
+    assert_eq!(cpus.len(), 1);
+    let cpu = cpus[0];
+    assert_eq!(cpu.path(), "/cpus/cpu@0");
+    assert_eq!(cpu.unit_address(), Some("0"));
+    assert_eq!(
+        cpu.reg().unwrap().collect::<Vec<_>>(),
+        [FdtReg {
+            address: 0,
+            size: None
+        }]
+    );
+    let str_prop = |name| cpu.property(name).and_then(|prop| prop.as_str());
+    assert_eq!(str_prop("status"), Some("okay"));
+    assert_eq!(str_prop("riscv,isa"), Some("rv64imafdcvsu"));
+    assert_eq!(str_prop("mmu-type"), Some("riscv,sv39"));
+    assert_eq!(cpu.phandle(), Some(0x08));
+
+    let intc = cpu.child("interrupt-controller").unwrap();
+    assert!(intc.is_compatible(&["riscv,cpu-intc"]));
+    assert_eq!(intc.phandle(), Some(0x0c));
+    assert_eq!(intc.property("interrupt-controller").unwrap().len(), 0);
+    assert_eq!(intc.parent().unwrap().path(), "/cpus/cpu@0");
+}
+
+#[test]
+fn memory_node() {
+    let fdt = sunxi();
+    let memory = fdt.find_node("/memory@40000000").unwrap();
+    assert_eq!(
+        memory.property("device_type").unwrap().as_str(),
+        Some("memory")
+    );
+    assert_eq!(
+        memory.reg().unwrap().collect::<Vec<_>>(),
+        [FdtReg {
+            address: 0x4000_0000,
+            size: Some(0x800_0000)
+        }]
+    );
+    /* without a unit address the name alone finds it */
+    assert_eq!(fdt.find_node("/memory").unwrap().path(), memory.path());
+}
+
+#[test]
+fn uarts_by_alias_and_compatible() {
+    let fdt = sunxi();
+    let uart = fdt.find_node("serial0").unwrap();
+    assert_eq!(uart.path(), "/soc@3000000/uart@2500000");
+    assert_eq!(
+        uart.reg().unwrap().next(),
+        Some(FdtReg {
+            address: 0x250_0000,
+            size: Some(0x400)
+        })
+    );
+    assert_eq!(uart.property("sunxi,uart-fifosize").unwrap().as_u32(), Some(0x40));
+    assert_eq!(
+        uart.property("pinctrl-names")
+            .unwrap()
+            .as_strings()
+            .collect::<Vec<_>>(),
+        ["default", "sleep"]
+    );
+    let uarts = fdt.find_all_compatible(&["allwinner,sun20i-uart"]).count();
+    assert_eq!(uarts, 6);
+    assert_eq!(
+        fdt.find_compatible(&["allwinner,sun20i-uart"]).unwrap().path(),
+        uart.path()
+    );
+}
+
+#[test]
+fn phandles() {
+    let fdt = sunxi();
+    let intc = fdt.find_phandle(0x0c).unwrap();
+    assert_eq!(intc.path(), "/cpus/cpu@0/interrupt-controller");
+    assert!(fdt.find_phandle(0xffff_fff0).is_none());
+}
+
+#[test]
+fn builder_round_trip() {
+    let fdt = sunxi();
+    let tree = FdtBuilder::from_fdt(&fdt).unwrap();
+    let buf = leak_aligned(&vec![0u8; SUNXI_DTB.len() * 2]);
+    let size = tree.serialize(buf).unwrap();
+    let copy = Fdt::from_bytes(&buf[..size]).unwrap();
+
+    let props = |fdt: &Fdt| {
+        fdt.node_iter()
+            .flat_map(|node| {
+                let path = node.path();
+                node.props()
+                    .map(move |prop| (path.clone(), prop.name(), prop.value().to_vec()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(props(&copy), props(&fdt));
+    assert!(copy
+        .memory_reserve_iter()
+        .eq(fdt.memory_reserve_iter()));
+    assert_eq!(
+        copy.header().boot_cpuid_phys(),
+        fdt.header().boot_cpuid_phys()
+    );
+}
+
+#[test]
+fn overlay_with_local_fixups() {
+    let mut overlay = FdtBuilder::from_fdt(&sunxi()).unwrap();
+    for name in ["aliases", "chosen", "cpus", "dram", "soc@3000000"] {
+        overlay.root_mut().remove_child(name);
+    }
+    overlay.root_mut().retain_children(|_| false);
+    let fragment = overlay.add_node("/fragment@0");
+    fragment.set_str("target-path", "/soc@3000000");
+    let content = overlay.add_node("/fragment@0/__overlay__");
+    content.set_u32("coffer,test", 1);
+    let node = content.add_child("test@0");
+    node.set_u32("phandle", 1);
+    node.set_cells("link", &[1]);
+    overlay
+        .add_node("/__local_fixups__/fragment@0/__overlay__/test@0")
+        .set_cells("link", &[0]);
+    let buf = leak_aligned(&vec![0u8; 4096]);
+    let size = overlay.serialize(buf).unwrap();
+    let overlay = Fdt::from_bytes(&buf[..size]).unwrap();
+
+    let base = sunxi();
+    let mut tree = FdtBuilder::from_fdt(&base).unwrap();
+    apply_overlay(&mut tree, &overlay).unwrap();
+
+    let soc = tree.node("/soc@3000000").unwrap();
+    assert_eq!(soc.property_u32("coffer,test"), Some(1));
+    let test = tree.node("/soc@3000000/test@0").unwrap();
+    let phandle = test.property_u32("phandle").unwrap();
+    /* moved above every phandle of the base tree, the reference followed it */
+    assert!(base.node_iter().all(|node| node.phandle() < Some(phandle)));
+    assert_eq!(test.property_u32("link"), Some(phandle));
+    assert!(tree.node("/fragment@0").is_none());
+}
+
+#[test]
+fn truncated_blobs_are_rejected() {
+    for len in (0..SUNXI_DTB.len()).step_by(97) {
+        assert!(Fdt::from_bytes(leak_aligned(&SUNXI_DTB[..len])).is_err());
+    }
+}
+
+#[test]
+fn bad_headers_are_rejected() {
+    let patched = |offset: usize, value: u32| {
+        let buf = leak_aligned(SUNXI_DTB);
+        buf[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+        Fdt::from_bytes(buf)
+    };
+    assert!(patched(0, 0xfeed_d00d).is_err());
+    /* total_size past the buffer */
+    assert!(patched(4, SUNXI_DTB.len() as u32 + 4).is_err());
+    /* structure block, strings block and reservation map past total_size */
+    assert!(patched(8, u32::MAX - 3).is_err());
+    assert!(patched(12, SUNXI_DTB.len() as u32).is_err());
+    assert!(patched(16, SUNXI_DTB.len() as u32 + 8).is_err());
+    assert!(patched(32, u32::MAX).is_err());
+    assert!(patched(36, u32::MAX).is_err());
+    /* misaligned */
+    let buf = leak_aligned(&[&[0u8][..], SUNXI_DTB].concat());
+    assert!(Fdt::from_bytes(&buf[1..]).is_err());
+}
+
+/* whatever the structure and strings blocks hold, walking them stays inside the blob */
+#[test]
+fn corrupted_blocks_do_not_panic() {
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    for _ in 0..200 {
+        let buf = leak_aligned(SUNXI_DTB);
+        for _ in 0..16 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            let offset = 40 + (seed as usize >> 8) % (buf.len() - 40);
+            buf[offset] = seed as u8;
+        }
+        if let Ok(fdt) = Fdt::from_bytes(buf) {
+            walk(&fdt);
+        }
+    }
+}
+
+fn walk(fdt: &Fdt) {
+    fdt.memory_reserve_iter().count();
+    for node in fdt.node_iter() {
+        node.path();
+        node.compatible().count();
+        node.reg().map(|reg| reg.count());
+        for prop in node.props() {
+            prop.name();
+            prop.as_strings().count();
+        }
+    }
+    fdt.find_node("serial0");
+    if let Ok(tree) = FdtBuilder::from_fdt(fdt) {
+        let buf = leak_aligned(&vec![0u8; SUNXI_DTB.len() * 2]);
+        let _ = tree.serialize(buf);
+    }
+}
+
+/* a bare version 17 blob: `levels` nodes each inside the one before, no properties */
+fn nested_blob(levels: usize) -> &'static [u8] {
+    let mut structure = Vec::new();
+    for _ in 0..levels {
+        structure.extend_from_slice(&1u32.to_be_bytes());
+        structure.extend_from_slice(b"n\0\0\0");
+    }
+    for _ in 0..levels {
+        structure.extend_from_slice(&2u32.to_be_bytes());
+    }
+    structure.extend_from_slice(&9u32.to_be_bytes());
+    let (off_rsvmap, off_struct) = (40u32, 56u32);
+    let off_strings = off_struct + structure.len() as u32;
+    let header = [
+        0xd00d_feed,
+        off_strings,
+        off_struct,
+        off_strings,
+        off_rsvmap,
+        17,
+        16,
+        0,
+        0,
+        structure.len() as u32,
+    ];
+    let mut blob: Vec<u8> = header.iter().flat_map(|word| word.to_be_bytes()).collect();
+    blob.extend_from_slice(&[0u8; 16]);
+    blob.extend_from_slice(&structure);
+    leak_aligned(&blob)
+}
+
+#[test]
+fn deep_trees_are_refused_not_recursed() {
+    let shallow = Fdt::from_bytes(nested_blob(coffer_fdt::builder::MAX_DEPTH)).unwrap();
+    let tree = FdtBuilder::from_fdt(&shallow).unwrap();
+    assert!(tree.serialize(leak_aligned(&[0u8; 4096])).is_ok());
+
+    let deep = Fdt::from_bytes(nested_blob(10_000)).unwrap();
+    assert!(FdtBuilder::from_fdt(&deep).is_err());
+
+    /* edits can nest past anything parsed, writing refuses those too */
+    let mut tree = FdtBuilder::from_fdt(&sunxi()).unwrap();
+    tree.add_node(&"/n".repeat(coffer_fdt::builder::MAX_DEPTH));
+    assert!(tree.serialize(leak_aligned(&vec![0u8; SUNXI_DTB.len() * 2])).is_err());
+}
+
+/* a name offset past the strings block reads nothing, even with bytes behind it */
+#[test]
+fn names_stay_inside_the_strings_block() {
+    let fdt = sunxi();
+    let (_, size) = fdt.header().strings_range();
+    assert_eq!(fdt.str_at_offset(size), None);
+    assert_eq!(fdt.str_at_offset(usize::MAX), None);
+    let first = fdt.node_iter().flat_map(|node| node.props()).next().unwrap();
+    assert_eq!(fdt.str_at_offset(first.name_offset()), Some(first.name()));
+}
+
+#[test]
+fn aliases_resolve_once_to_full_paths() {
+    let mut tree = FdtBuilder::from_fdt(&sunxi()).unwrap();
+    let aliases = tree.node_mut("/aliases").unwrap();
+    aliases.set_str("loop", "loop");
+    aliases.set_str("indirect", "serial0");
+    aliases.set_str("soc", "/soc@3000000");
+    let buf = leak_aligned(&vec![0u8; SUNXI_DTB.len() * 2]);
+    let size = tree.serialize(buf).unwrap();
+    let fdt = Fdt::from_bytes(&buf[..size]).unwrap();
+    assert!(fdt.find_node("loop").is_none());
+    assert!(fdt.find_node("loop/uart@2500000").is_none());
+    assert!(fdt.find_node("indirect").is_none());
+    assert_eq!(
+        fdt.find_node("soc/uart@2500000").unwrap().path(),
+        "/soc@3000000/uart@2500000"
+    );
+}
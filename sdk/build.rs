@@ -0,0 +1,9 @@
+use std::{env, fs, path::Path};
+
+/* put enclave.ld where the linker of the final image finds it */
+fn main() {
+    let out = env::var("OUT_DIR").unwrap();
+    fs::copy("enclave.ld", Path::new(&out).join("enclave.ld")).unwrap();
+    println!("cargo:rustc-link-search={}", out);
+    println!("cargo:rerun-if-changed=enclave.ld");
+}
@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use coffer_enclave::{attest, entry, mailbox};
+
+entry!(main);
+
+/* thread 0 proves who it is, thread 1 waits for a peer and echoes its sender */
+fn main(tid: usize) -> usize {
+    match tid {
+        0 => hello(),
+        _ => match mailbox::receive() {
+            Ok(message) => message.from,
+            Err(_) => usize::MAX,
+        },
+    }
+}
+
+fn hello() -> usize {
+    let mut report_data = [0u8; 64];
+    report_data[..5].copy_from_slice(b"hello");
+    let report = match attest::attest(&report_data) {
+        Ok(report) => report,
+        Err(_) => return 1,
+    };
+    let key = match attest::seal_key(&[0u8; 32]) {
+        Ok(key) => key,
+        Err(_) => return 2,
+    };
+    /* exercise the heap past its initial size */
+    let squares: Vec<u64> = (0..32 * 1024).map(|i| i * i).collect();
+    let checksum = squares.iter().fold(0u64, |acc, x| acc.wrapping_add(*x));
+    (checksum as usize ^ key[0] as usize ^ report.measurement[0] as usize) & 0xff | 0x100
+}
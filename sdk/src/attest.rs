@@ -0,0 +1,32 @@
+use coffer_common::{
+    report::{Report, REPORT_DATA_SIZE, REPORT_SIZE},
+    seal::{SEAL_KEY_SIZE, SEAL_LABEL_SIZE},
+};
+
+use crate::ecall::{ecall, Error, Result, FID_ATTEST, FID_SEAL_KEY};
+
+/* a report over this image and `report_data`, signed with the device key */
+pub fn attest(report_data: &[u8; REPORT_DATA_SIZE]) -> Result<Report> {
+    let mut out = [0u8; REPORT_SIZE];
+    unsafe {
+        ecall(
+            FID_ATTEST,
+            report_data.as_ptr() as usize,
+            out.as_mut_ptr() as usize,
+        )?
+    };
+    Report::from_bytes(&out).ok_or(Error::Failed)
+}
+
+/* the same image gets the same key for the same `label` on this device, across reboots */
+pub fn seal_key(label: &[u8; SEAL_LABEL_SIZE]) -> Result<[u8; SEAL_KEY_SIZE]> {
+    let mut key = [0u8; SEAL_KEY_SIZE];
+    unsafe {
+        ecall(
+            FID_SEAL_KEY,
+            label.as_ptr() as usize,
+            key.as_mut_ptr() as usize,
+        )?
+    };
+    Ok(key)
+}
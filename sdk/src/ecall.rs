@@ -0,0 +1,88 @@
+use core::arch::asm;
+
+/* "\tCOF", the vendor extension coffer answers on */
+pub const EXT_COFFER: usize = 0x0943_4F46;
+
+pub const FID_EXIT: usize = 0x0;
+pub const FID_OCALL: usize = 0x1;
+pub const FID_TRAP_REGISTER: usize = 0x2;
+pub const FID_TRAP_RETURN: usize = 0x3;
+pub const FID_SHM_ADDR: usize = 0x10;
+pub const FID_SHM_SIZE: usize = 0x11;
+pub const FID_SHM_NOTIFY: usize = 0x12;
+pub const FID_SHM_POLL: usize = 0x13;
+pub const FID_MEMORY_GROW: usize = 0x20;
+pub const FID_MEMORY_RELEASE: usize = 0x21;
+pub const FID_MAIL_SEND: usize = 0x30;
+pub const FID_MAIL_RECEIVE: usize = 0x31;
+pub const FID_ATTEST: usize = 0x40;
+pub const FID_SEAL_KEY: usize = 0x41;
+
+/* the SBI error codes coffer returns */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    Failed,
+    NotSupported,
+    InvalidParam,
+    Denied,
+    InvalidAddress,
+    Other(isize),
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+impl Error {
+    fn from_code(error: usize) -> Self {
+        match error as isize {
+            -1 => Error::Failed,
+            -2 => Error::NotSupported,
+            -3 => Error::InvalidParam,
+            -4 => Error::Denied,
+            -5 => Error::InvalidAddress,
+            code => Error::Other(code),
+        }
+    }
+}
+
+/* a7 = extension, a6 = function, the result comes back as (a0 = error, a1 = value) */
+pub unsafe fn ecall(fid: usize, arg0: usize, arg1: usize) -> Result<usize> {
+    let (error, value);
+    asm!(
+        "ecall",
+        inlateout("a0") arg0 => error,
+        inlateout("a1") arg1 => value,
+        in("a6") fid,
+        in("a7") EXT_COFFER,
+    );
+    match error {
+        0 => Ok(value),
+        error => Err(Error::from_code(error)),
+    }
+}
+
+/* leave the enclave for good, the host sees `value` as the exit payload */
+pub fn exit(value: usize) -> ! {
+    unsafe {
+        let _ = ecall(FID_EXIT, value, 0);
+    }
+    unreachable!()
+}
+
+/* ask the host for service `number`, arguments go through shared channel `channel` */
+pub fn ocall(number: usize, channel: usize) -> Result<usize> {
+    unsafe { ecall(FID_OCALL, number, channel) }
+}
+
+/* `handler` gets the exceptions in `delegated` (an mcause bitmap) instead of coffer */
+pub fn trap_register(handler: usize, delegated: usize) -> Result<()> {
+    unsafe { ecall(FID_TRAP_REGISTER, handler, delegated).map(|_| ()) }
+}
+
+/* leave the trap handler for `epc`, zero retries the trapping instruction;
+ * only comes back when there is no trap to return from */
+pub unsafe fn trap_return(epc: usize) -> Error {
+    match ecall(FID_TRAP_RETURN, epc, 0) {
+        Err(error) => error,
+        Ok(_) => unreachable!(),
+    }
+}
@@ -0,0 +1,95 @@
+use core::{
+    arch::global_asm,
+    panic::PanicInfo,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use crate::{ecall::exit, heap};
+
+/* exit values the host sees when the runtime gives up on the enclave */
+pub const PANIC_EXIT: usize = usize::MAX;
+pub const ALLOC_EXIT: usize = usize::MAX - 1;
+
+/* R_RISCV_RELATIVE, the only relocation a static PIE image carries */
+global_asm!(
+    "
+    .section .text.entry
+    .globl _start
+_start:
+    /* a0: enclave id, a1: thread id, sp: top of this thread's stack */
+    .option push
+    .option norelax
+    lla     gp, __global_pointer$
+    .option pop
+    andi    sp, sp, -16
+    bnez    a1, 3f
+    lla     t0, __enclave_start
+    lla     t1, __rela_dyn_start
+    lla     t2, __rela_dyn_end
+1:
+    bgeu    t1, t2, 3f
+    ld      t3, 8(t1)
+    li      t4, 3
+    bne     t3, t4, 2f
+    ld      t3, 0(t1)
+    ld      t4, 16(t1)
+    add     t3, t3, t0
+    add     t4, t4, t0
+    sd      t4, 0(t3)
+    addi    t1, t1, 24
+    j       1b
+2:
+    /* anything else cannot be resolved without a loader */
+    unimp
+3:
+    call    _coffer_enclave_start
+    "
+);
+
+static EID: AtomicUsize = AtomicUsize::new(0);
+/* set by thread 0 once relocations and the heap are in place */
+static READY: AtomicBool = AtomicBool::new(false);
+
+pub fn enclave_id() -> usize {
+    EID.load(Ordering::Relaxed)
+}
+
+#[no_mangle]
+extern "C" fn _coffer_enclave_start(eid: usize, tid: usize) -> ! {
+    if tid == 0 {
+        EID.store(eid, Ordering::Relaxed);
+        unsafe { heap::init() };
+        READY.store(true, Ordering::Release);
+    } else {
+        /* other threads may be entered first, they wait for thread 0 to set up */
+        while !READY.load(Ordering::Acquire) {
+            core::hint::spin_loop();
+        }
+    }
+    extern "Rust" {
+        fn __coffer_enclave_main(tid: usize) -> usize;
+    }
+    exit(unsafe { __coffer_enclave_main(tid) })
+}
+
+/* `entry!(main)` with `fn main(tid: usize) -> usize`, the result is the exit value */
+#[macro_export]
+macro_rules! entry {
+    ($main:path) => {
+        #[no_mangle]
+        pub fn __coffer_enclave_main(tid: usize) -> usize {
+            let main: fn(usize) -> usize = $main;
+            main(tid)
+        }
+    };
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    exit(PANIC_EXIT)
+}
+
+#[alloc_error_handler]
+fn alloc_error(_layout: core::alloc::Layout) -> ! {
+    exit(ALLOC_EXIT)
+}
@@ -0,0 +1,54 @@
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    ptr::{null_mut, NonNull},
+};
+
+use buddy_system_allocator::LockedHeap;
+
+use crate::memory;
+
+/* smallest amount borrowed from the memory pool at a time */
+const GROW_SIZE: usize = 64 * 1024;
+
+/* starts with the image's .heap section, grows through coffer when it runs dry */
+struct EnclaveHeap(LockedHeap<32>);
+
+#[global_allocator]
+static HEAP: EnclaveHeap = EnclaveHeap(LockedHeap::empty());
+
+extern "C" {
+    static mut __heap_start: u8;
+    static mut __heap_end: u8;
+}
+
+pub(crate) unsafe fn init() {
+    let start = &__heap_start as *const u8 as usize;
+    let end = &__heap_end as *const u8 as usize;
+    HEAP.0.lock().add_to_heap(start, end);
+}
+
+unsafe impl GlobalAlloc for EnclaveHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.0.lock();
+        if let Ok(ptr) = heap.alloc(layout) {
+            return ptr.as_ptr();
+        }
+        /* grown chunks are naturally aligned, so any alignment up to their size holds */
+        let len = layout
+            .size()
+            .max(layout.align())
+            .next_power_of_two()
+            .max(GROW_SIZE);
+        match memory::grow(len) {
+            Ok(addr) => {
+                heap.add_to_heap(addr, addr + len);
+                heap.alloc(layout).map_or(null_mut(), |ptr| ptr.as_ptr())
+            }
+            Err(_) => null_mut(),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.lock().dealloc(NonNull::new_unchecked(ptr), layout)
+    }
+}
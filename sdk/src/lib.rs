@@ -0,0 +1,16 @@
+#![no_std]
+#![feature(alloc_error_handler)]
+
+extern crate alloc;
+
+pub mod attest;
+pub mod ecall;
+mod entry;
+mod heap;
+pub mod mailbox;
+pub mod memory;
+pub mod shm;
+
+pub use coffer_common::{measure::Measurement, report::Report};
+pub use ecall::{exit, ocall, Error, Result};
+pub use entry::enclave_id;
@@ -0,0 +1,50 @@
+use crate::{
+    ecall::{ecall, Result, FID_MAIL_RECEIVE, FID_MAIL_SEND},
+    Measurement,
+};
+
+pub const MESSAGE_SIZE: usize = 64;
+
+/* filled in by coffer, the sender cannot forge `from` or `measurement` */
+#[repr(C)]
+pub struct Message {
+    pub from: usize,
+    pub measurement: Measurement,
+    pub data: [u8; MESSAGE_SIZE],
+}
+
+impl Message {
+    const fn empty() -> Self {
+        Message {
+            from: 0,
+            measurement: [0; 32],
+            data: [0; MESSAGE_SIZE],
+        }
+    }
+}
+
+pub fn send(to: usize, data: &[u8; MESSAGE_SIZE]) -> Result<()> {
+    unsafe { ecall(FID_MAIL_SEND, to, data.as_ptr() as usize).map(|_| ()) }
+}
+
+/* `Err(Error::Failed)` when nothing is waiting */
+pub fn try_receive() -> Result<Message> {
+    receive_with(false)
+}
+
+/* leaves the enclave until a message arrives, the host enters again to retry */
+pub fn receive() -> Result<Message> {
+    receive_with(true)
+}
+
+fn receive_with(block: bool) -> Result<Message> {
+    let mut message = Message::empty();
+    unsafe {
+        ecall(
+            FID_MAIL_RECEIVE,
+            &mut message as *mut Message as usize,
+            block as usize,
+        )?
+    };
+    Ok(message)
+}
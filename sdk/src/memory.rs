@@ -0,0 +1,11 @@
+use crate::ecall::{ecall, Result, FID_MEMORY_GROW, FID_MEMORY_RELEASE};
+
+/* `len` (a power of two, at least a page) more bytes from the pool, naturally aligned */
+pub fn grow(len: usize) -> Result<usize> {
+    unsafe { ecall(FID_MEMORY_GROW, len, 0) }
+}
+
+/* hand back a napot piece of what `grow` returned, it is zeroed on the way out */
+pub unsafe fn release(addr: usize, len: usize) -> Result<()> {
+    ecall(FID_MEMORY_RELEASE, addr, len).map(|_| ())
+}
@@ -0,0 +1,34 @@
+use core::slice;
+
+use crate::ecall::{ecall, Result, FID_SHM_ADDR, FID_SHM_NOTIFY, FID_SHM_POLL, FID_SHM_SIZE};
+
+/* a buffer the host created for this enclave, both sides read and write it */
+pub struct Channel {
+    pub id: usize,
+    addr: usize,
+    size: usize,
+}
+
+impl Channel {
+    pub fn open(id: usize) -> Result<Self> {
+        unsafe {
+            let addr = ecall(FID_SHM_ADDR, id, 0)?;
+            let size = ecall(FID_SHM_SIZE, id, 0)?;
+            Ok(Channel { id, addr, size })
+        }
+    }
+
+    /* the host may change the contents at any time, copy before checking */
+    pub fn buffer(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.addr as *mut u8, self.size) }
+    }
+
+    pub fn notify(&self) -> Result<()> {
+        unsafe { ecall(FID_SHM_NOTIFY, self.id, 0).map(|_| ()) }
+    }
+
+    /* true when the host rang since the last poll */
+    pub fn poll(&self) -> Result<bool> {
+        unsafe { ecall(FID_SHM_POLL, self.id, 0).map(|pending| pending != 0) }
+    }
+}
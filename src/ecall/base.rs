@@ -5,8 +5,8 @@ use crate::sbi::ipi::probe_ipi;
 use crate::sbi::rfence::probe_rfence;
 use crate::sbi::srst::probe_srst;
 use crate::sbi::{
-    sbiret::SbiRet, timer::probe_timer, COFFER_IMPL_ID, COFFER_VERSION, SBI_SPEC_MAJOR,
-    SBI_SPEC_MINOR,
+    sbiret::SbiRet, timer::probe_timer, COFFER_IMPL_ID, COFFER_VERSION, EXT_BASE, EXT_COFFER,
+    EXT_HSM, EXT_IPI, EXT_RFENCE, EXT_SRST, EXT_TIME, SBI_SPEC_MAJOR, SBI_SPEC_MINOR,
 };
 
 const FID_BASE_GET_SPEC_VERSION: usize = 0x0;
@@ -50,11 +50,12 @@ fn get_sbi_impl_version() -> SbiRet {
 fn probe_extension(ext_id: usize) -> SbiRet {
     match ext_id {
         EXT_BASE => SbiRet::ok(1),
-        EXT_TIMER => probe_timer(),
+        EXT_TIME => probe_timer(),
         EXT_IPI => probe_ipi(),
         EXT_HSM => probe_hsm(),
         EXT_SRST => probe_srst(),
         EXT_RFENCE => probe_rfence(),
+        EXT_COFFER => SbiRet::ok(1),
         _ => SbiRet::ok(0),
     }
 }
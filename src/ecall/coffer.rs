@@ -0,0 +1,65 @@
+use crate::enclave::{
+    attest, audit, create_enclave, create_thread, destroy_enclave, enter_enclave, loader, memory,
+    return_ocall, shm, EnclaveExit,
+};
+use crate::runtime::context::Context;
+use crate::sbi::sbiret::SbiRet;
+
+const FID_ENCLAVE_CREATE: usize = 0x0;
+const FID_ENCLAVE_ENTER: usize = 0x1;
+const FID_ENCLAVE_DESTROY: usize = 0x2;
+const FID_OCALL_RETURN: usize = 0x3;
+const FID_THREAD_CREATE: usize = 0x4;
+const FID_ENCLAVE_LOAD: usize = 0x6;
+const FID_DEVICE_KEY: usize = 0x7;
+const FID_AUDIT_READ: usize = 0x8;
+const FID_SHM_CREATE: usize = 0x10;
+const FID_SHM_DESTROY: usize = 0x11;
+const FID_SHM_NOTIFY: usize = 0x12;
+const FID_SHM_POLL: usize = 0x13;
+const FID_MEMORY_DONATE: usize = 0x20;
+const FID_MEMORY_RECLAIM: usize = 0x21;
+
+#[inline]
+pub fn handle_ecall_coffer(
+    ctx: *mut Context,
+    fid: usize,
+    param0: usize,
+    param1: usize,
+    param2: usize,
+    param3: usize,
+) -> SbiRet {
+    match fid {
+        FID_ENCLAVE_CREATE => create_enclave(param0, param1, param2, param3),
+        FID_ENCLAVE_ENTER => report_exit(ctx, enter_enclave(param0, param1)),
+        FID_ENCLAVE_DESTROY => destroy_enclave(param0),
+        FID_OCALL_RETURN => report_exit(ctx, return_ocall(param0, param1, param2)),
+        FID_THREAD_CREATE => create_thread(param0, param1, param2),
+        FID_ENCLAVE_LOAD => loader::load_enclave(param0, param1, param2, param3),
+        FID_DEVICE_KEY => attest::device_public_key(param0),
+        FID_AUDIT_READ => audit::read_log(param0, param1, param2),
+        FID_SHM_CREATE => shm::create_channel(param0, param1, param2),
+        FID_SHM_DESTROY => shm::destroy_channel(param0),
+        FID_SHM_NOTIFY => shm::host_notify(param0),
+        FID_SHM_POLL => shm::host_poll(param0),
+        FID_MEMORY_DONATE => memory::donate(param0, param1),
+        FID_MEMORY_RECLAIM => memory::reclaim(param0, param1),
+        _ => SbiRet::not_supported(),
+    }
+}
+
+#[inline]
+fn report_exit(ctx: *mut Context, exit: Result<EnclaveExit, SbiRet>) -> SbiRet {
+    match exit {
+        Ok(exit) => {
+            /* a0: error, a1: exit reason, a2/a3: payload */
+            let (reason, payload0, payload1) = exit.to_reg();
+            unsafe {
+                (*ctx).a2 = payload0;
+                (*ctx).a3 = payload1;
+            }
+            SbiRet::ok(reason)
+        }
+        Err(sbi_ret) => sbi_ret,
+    }
+}
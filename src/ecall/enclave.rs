@@ -0,0 +1,87 @@
+use crate::enclave::{attest, get_enclave, mailbox, memory, shm, trap::TrapState, EnclaveExit, Ocall};
+use crate::runtime::context::Context;
+use crate::sbi::{sbiret::SbiRet, EXT_COFFER};
+
+const FID_EXIT: usize = 0x0;
+const FID_OCALL: usize = 0x1;
+const FID_TRAP_REGISTER: usize = 0x2;
+const FID_TRAP_RETURN: usize = 0x3;
+const FID_MEMORY_GROW: usize = 0x20;
+const FID_MEMORY_RELEASE: usize = 0x21;
+const FID_MAIL_SEND: usize = 0x30;
+const FID_MAIL_RECEIVE: usize = 0x31;
+const FID_ATTEST: usize = 0x40;
+const FID_SEAL_KEY: usize = 0x41;
+const FID_SHM_ADDR: usize = 0x10;
+const FID_SHM_SIZE: usize = 0x11;
+const FID_SHM_NOTIFY: usize = 0x12;
+const FID_SHM_POLL: usize = 0x13;
+
+/* ecalls issued from inside an enclave, `Err` leaves the enclave */
+pub fn handle_ecall_enclave(
+    eid: usize,
+    ctx: *mut Context,
+    trap: &mut TrapState,
+) -> Result<SbiRet, EnclaveExit> {
+    let (ext, fid, p0, p1) = unsafe { ((*ctx).a7, (*ctx).a6, (*ctx).a0, (*ctx).a1) };
+    if ext != EXT_COFFER {
+        return Ok(SbiRet::not_supported());
+    }
+    match fid {
+        FID_EXIT => Err(EnclaveExit::Exit(p0)),
+        FID_OCALL => ocall(eid, p0, p1),
+        FID_TRAP_REGISTER => Ok(register_trap(eid, trap, p0, p1)),
+        FID_TRAP_RETURN => Ok(trap.trap_return(unsafe { &mut *ctx }, p0)),
+        FID_SHM_ADDR => Ok(shm::enclave_channel_addr(eid, p0)),
+        FID_SHM_SIZE => Ok(shm::enclave_channel_size(eid, p0)),
+        FID_SHM_NOTIFY => Ok(shm::enclave_notify(eid, p0)),
+        FID_SHM_POLL => Ok(shm::enclave_poll(eid, p0)),
+        FID_MEMORY_GROW => Ok(memory::grow_enclave(eid, p0)),
+        FID_MEMORY_RELEASE => Ok(memory::shrink_enclave(eid, p0, p1)),
+        FID_MAIL_SEND => Ok(mailbox::send(eid, p0, p1)),
+        FID_MAIL_RECEIVE => receive(eid, ctx, p0, p1),
+        FID_ATTEST => Ok(attest::attest(eid, p0, p1)),
+        FID_SEAL_KEY => Ok(attest::seal_key(eid, p0, p1)),
+        _ => Ok(SbiRet::not_supported()),
+    }
+}
+
+/* arguments travel through a shared channel, the host only sees its address */
+fn ocall(eid: usize, number: usize, channel: usize) -> Result<SbiRet, EnclaveExit> {
+    let buffer = shm::enclave_channel_addr(eid, channel);
+    if buffer.error != 0 {
+        return Ok(buffer);
+    }
+    Err(EnclaveExit::Ocall(Ocall {
+        number,
+        buffer: buffer.value,
+    }))
+}
+
+/* a blocking receive leaves the enclave and reissues the ecall on the next entry */
+fn receive(
+    eid: usize,
+    ctx: *mut Context,
+    buffer: usize,
+    block: usize,
+) -> Result<SbiRet, EnclaveExit> {
+    match mailbox::receive(eid, buffer) {
+        Some(sbi_ret) => Ok(sbi_ret),
+        None if block != 0 => {
+            unsafe { (*ctx).mepc -= 4 };
+            Err(EnclaveExit::Waiting)
+        }
+        None => Ok(SbiRet::failed()),
+    }
+}
+
+/* the handler has to live inside enclave memory */
+fn register_trap(eid: usize, trap: &mut TrapState, handler: usize, delegated: usize) -> SbiRet {
+    match get_enclave(eid) {
+        Some(enclave) if enclave.memory.lock().contains(handler) => {
+            trap.register(handler, delegated)
+        }
+        Some(_) => SbiRet::invalid_address(),
+        None => SbiRet::invalid_param(),
+    }
+}
@@ -3,6 +3,7 @@ use crate::sbi::sbiret::SbiRet;
 use crate::{println, sbi::*};
 
 use self::base::handle_ecall_base;
+use self::coffer::handle_ecall_coffer;
 use self::hsm::handle_ecall_hsm;
 use self::ipi::{handle_ecall_ipi, FID_SEND_IPI};
 use self::rfence::handle_ecall_rfence;
@@ -10,12 +11,16 @@ use self::srst::handle_ecall_srst;
 use self::timer::{handle_ecall_timer, FID_SET_TIMER};
 
 mod base;
+mod coffer;
+mod enclave;
 mod hsm;
 mod ipi;
 mod rfence;
 mod srst;
 mod timer;
 
+pub use self::enclave::handle_ecall_enclave;
+
 pub fn handle_ecall(ctx: *mut Context) -> SbiRet {
     let (ext, fid, p0, p1, p2, p3, p4) = unsafe {
         (
@@ -35,6 +40,7 @@ pub fn handle_ecall(ctx: *mut Context) -> SbiRet {
         EXT_RFENCE => handle_ecall_rfence(fid, p0, p1, p2, p3, p4),
         EXT_HSM => handle_ecall_hsm(fid, p0, p1, p2),
         EXT_SRST => handle_ecall_srst(fid, p0, p1),
+        EXT_COFFER => handle_ecall_coffer(ctx, fid, p0, p1, p2, p3),
         LEGACY_TIMER => handle_ecall_timer(FID_SET_TIMER, p0).legacy_void(p0, p1),
         LEGACY_GETCHAR => SbiRet {
             error: console_getchar() as usize,
@@ -0,0 +1,90 @@
+use coffer_common::{
+    report::{Report, REPORT_DATA_SIZE, REPORT_SIZE},
+    seal::{seal_key as derive_seal_key, SEAL_KEY_SIZE, SEAL_LABEL_SIZE},
+};
+use ed25519_dalek::{ExpandedSecretKey, PublicKey, SecretKey};
+
+use super::{
+    audit::{record, AuditEvent, NO_THREAD},
+    get_enclave,
+    measure::Measurement,
+    overlaps_private,
+};
+use crate::{memory::memory_layout::coffer_range, sbi::sbiret::SbiRet};
+
+/* seed of the device attestation key, baked in at build time by build.rs */
+const DEVICE_SEED: [u8; 32] = include!(concat!(env!("OUT_DIR"), "/device_seed.rs"));
+
+lazy_static::lazy_static! {
+    static ref DEVICE_KEY: (ExpandedSecretKey, PublicKey) = {
+        let secret = SecretKey::from_bytes(&DEVICE_SEED).unwrap();
+        (ExpandedSecretKey::from(&secret), PublicKey::from(&secret))
+    };
+}
+
+/* both buffers in the caller's private memory, then its measurement */
+fn enclave_measurement(eid: usize, buffers: &[(usize, usize)]) -> Result<Measurement, SbiRet> {
+    let enclave = get_enclave(eid).ok_or(SbiRet::invalid_param())?;
+    let owns = |&(addr, len): &(usize, usize)| match addr.checked_add(len) {
+        Some(end) => enclave.memory.lock().contains_range(&(addr..end)),
+        None => false,
+    };
+    if !buffers.iter().all(owns) {
+        return Err(SbiRet::invalid_address());
+    }
+    enclave.measurement.get().copied().ok_or(SbiRet::denied())
+}
+
+/* sign the caller's measurement together with 64 bytes of its choosing */
+pub fn attest(eid: usize, report_data: usize, out: usize) -> SbiRet {
+    let measurement =
+        match enclave_measurement(eid, &[(report_data, REPORT_DATA_SIZE), (out, REPORT_SIZE)]) {
+            Ok(measurement) => measurement,
+            Err(sbi_ret) => return sbi_ret,
+        };
+    let mut data = [0u8; REPORT_DATA_SIZE];
+    unsafe {
+        core::ptr::copy_nonoverlapping(report_data as *const u8, data.as_mut_ptr(), data.len())
+    };
+    let (secret, public) = &*DEVICE_KEY;
+    let signature = secret.sign(&Report::signed_bytes(&measurement, &data), public);
+    let report = Report {
+        measurement,
+        report_data: data,
+        signature: signature.to_bytes(),
+    };
+    unsafe {
+        core::ptr::copy_nonoverlapping(report.to_bytes().as_ptr(), out as *mut u8, REPORT_SIZE)
+    };
+    record(AuditEvent::Attest, eid, NO_THREAD, 0);
+    SbiRet::ok(0)
+}
+
+/* a key only this image on this device can derive, `label` picks one of many */
+pub fn seal_key(eid: usize, label: usize, out: usize) -> SbiRet {
+    let measurement =
+        match enclave_measurement(eid, &[(label, SEAL_LABEL_SIZE), (out, SEAL_KEY_SIZE)]) {
+            Ok(measurement) => measurement,
+            Err(sbi_ret) => return sbi_ret,
+        };
+    let mut bytes = [0u8; SEAL_LABEL_SIZE];
+    unsafe { core::ptr::copy_nonoverlapping(label as *const u8, bytes.as_mut_ptr(), bytes.len()) };
+    let key = derive_seal_key(&DEVICE_SEED, &measurement, &bytes);
+    unsafe { core::ptr::copy_nonoverlapping(key.as_ptr(), out as *mut u8, key.len()) };
+    SbiRet::ok(0)
+}
+
+/* the host publishes this so verifiers can check reports */
+pub fn device_public_key(out: usize) -> SbiRet {
+    let bytes = DEVICE_KEY.1.to_bytes();
+    let range = match out.checked_add(bytes.len()) {
+        Some(end) => out..end,
+        None => return SbiRet::invalid_address(),
+    };
+    let coffer = coffer_range();
+    if (coffer.start < range.end && range.start < coffer.end) || overlaps_private(&range) {
+        return SbiRet::denied();
+    }
+    unsafe { core::ptr::copy_nonoverlapping(bytes.as_ptr(), out as *mut u8, bytes.len()) };
+    SbiRet::ok(0)
+}
@@ -0,0 +1,108 @@
+use riscv::register::mhartid;
+use spin::Mutex;
+
+use super::overlaps_private;
+use crate::{
+    memory::memory_layout::coffer_range,
+    sbi::{sbiret::SbiRet, timer::read_mtime},
+};
+
+/* entries kept, older ones are overwritten */
+pub const AUDIT_DEPTH: usize = 256;
+/* no thread involved, e.g. create or destroy */
+pub const NO_THREAD: usize = usize::MAX;
+
+#[derive(Clone, Copy)]
+pub enum AuditEvent {
+    Create = 0,
+    Enter = 1,
+    /* detail: exit reason as reported to the host */
+    Exit = 2,
+    /* detail: `EnclaveFault` */
+    Fault = 3,
+    Destroy = 4,
+    /* a thread resumed on another hart, detail: the hart it ran on before */
+    Migrate = 5,
+    Attest = 6,
+}
+
+/* what the host reads, nothing in here comes from enclave memory */
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct AuditEntry {
+    /* increases by one per event, gaps tell the host it fell behind */
+    pub seq: u64,
+    /* mtime */
+    pub time: u64,
+    pub hart: usize,
+    pub eid: usize,
+    pub tid: usize,
+    pub event: usize,
+    pub detail: usize,
+}
+
+impl AuditEntry {
+    const fn empty() -> Self {
+        AuditEntry {
+            seq: 0,
+            time: 0,
+            hart: 0,
+            eid: 0,
+            tid: 0,
+            event: 0,
+            detail: 0,
+        }
+    }
+}
+
+struct AuditLog {
+    entries: [AuditEntry; AUDIT_DEPTH],
+    /* sequence number of the next event */
+    next: u64,
+}
+
+static AUDIT_LOG: Mutex<AuditLog> = Mutex::new(AuditLog {
+    entries: [AuditEntry::empty(); AUDIT_DEPTH],
+    next: 0,
+});
+
+pub fn record(event: AuditEvent, eid: usize, tid: usize, detail: usize) {
+    let entry = AuditEntry {
+        seq: 0,
+        time: read_mtime(),
+        hart: mhartid::read(),
+        eid,
+        tid,
+        event: event as usize,
+        detail,
+    };
+    let mut log = AUDIT_LOG.lock();
+    let seq = log.next;
+    log.entries[seq as usize % AUDIT_DEPTH] = AuditEntry { seq, ..entry };
+    log.next += 1;
+}
+
+/* copy up to `count` entries starting at sequence `from` (or the oldest one still
+ * kept) into a host buffer, returns how many were copied */
+pub fn read_log(from: usize, buffer: usize, count: usize) -> SbiRet {
+    let range = match count
+        .checked_mul(core::mem::size_of::<AuditEntry>())
+        .and_then(|len| buffer.checked_add(len))
+    {
+        Some(end) => buffer..end,
+        None => return SbiRet::invalid_param(),
+    };
+    let coffer = coffer_range();
+    if (coffer.start < range.end && range.start < coffer.end) || overlaps_private(&range) {
+        return SbiRet::denied();
+    }
+    let log = AUDIT_LOG.lock();
+    let oldest = log.next.saturating_sub(AUDIT_DEPTH as u64);
+    let start = (from as u64).max(oldest);
+    let copied = log.next.saturating_sub(start).min(count as u64);
+    for (i, seq) in (start..start + copied).enumerate() {
+        let entry = log.entries[seq as usize % AUDIT_DEPTH];
+        unsafe { core::ptr::write_unaligned((buffer as *mut AuditEntry).add(i), entry) };
+    }
+    SbiRet::ok(copied as usize)
+}
@@ -0,0 +1,117 @@
+use coffer_common::image::{Image, PAGE_SIZE};
+use goblin::elf::program_header;
+
+use super::{overlaps_private, register_enclave, scrub::ScrubFlags};
+use crate::{
+    memory::{
+        memory_layout::{coffer_range, napot_blocks, MemoryLayout, Region},
+        pmp::PmpFlags,
+    },
+    sbi::sbiret::SbiRet,
+};
+
+/* pmp entries the segments of one image may take from the enclave layout */
+const MAX_SEGMENT_REGIONS: usize = 8;
+
+fn pmp_flags(p_flags: u32) -> PmpFlags {
+    let mut flags = PmpFlags::empty();
+    if p_flags & program_header::PF_R != 0 {
+        flags |= PmpFlags::READABLE;
+    }
+    if p_flags & program_header::PF_W != 0 {
+        flags |= PmpFlags::WRITABLE;
+    }
+    if p_flags & program_header::PF_X != 0 {
+        flags |= PmpFlags::EXECUTABLE;
+    }
+    flags
+}
+
+/* segment permissions go in front of the RW region, lower slots win */
+fn segment_layout(base: usize, image: &Image) -> Result<MemoryLayout, &'static str> {
+    let mut layout = MemoryLayout::new();
+    let mut used = 0;
+    for segment in image.segments.iter() {
+        let start = segment.offset & !(PAGE_SIZE - 1);
+        let end = (segment.offset + segment.mem_size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+        for block in napot_blocks(start..end) {
+            used += 1;
+            if used > MAX_SEGMENT_REGIONS {
+                return Err("[ERROR]: segments are not napot friendly");
+            }
+            let region = Region::napot(
+                base + block.start,
+                block.end - block.start,
+                pmp_flags(segment.flags),
+            )?;
+            layout.add_region(region)?;
+        }
+    }
+    Ok(layout)
+}
+
+pub fn load_enclave(bundle: usize, bundle_size: usize, base: usize, time_slice: usize) -> SbiRet {
+    /* the bundle is read from host memory, never from coffer or another enclave */
+    let bundle_range = match bundle.checked_add(bundle_size) {
+        Some(end) => bundle..end,
+        None => return SbiRet::invalid_param(),
+    };
+    let coffer = coffer_range();
+    if (coffer.start < bundle_range.end && bundle_range.start < coffer.end)
+        || overlaps_private(&bundle_range)
+    {
+        return SbiRet::denied();
+    }
+    let bundle = unsafe { core::slice::from_raw_parts(bundle as *const u8, bundle_size) };
+    let image = match Image::parse(bundle) {
+        Ok(image) => image,
+        Err(_) => return SbiRet::invalid_param(),
+    };
+    let manifest = image.manifest;
+    /* heap and stack, only the segments in front of it may be executable */
+    let region = match Region::napot(
+        base,
+        manifest.memory_size as usize,
+        PmpFlags::READABLE | PmpFlags::WRITABLE,
+    ) {
+        Ok(region) => region,
+        Err(_) => return SbiRet::invalid_param(),
+    };
+    if region.overlaps(&bundle_range) {
+        return SbiRet::denied();
+    }
+    let layout = match segment_layout(base, &image) {
+        Ok(layout) => layout,
+        Err(_) => return SbiRet::invalid_param(),
+    };
+
+    /* part of the measurement, the host cannot weaken it */
+    let scrub = ScrubFlags::all() - ScrubFlags::from_bits_truncate(manifest.scrub_exempt as usize);
+    let enclave = match register_enclave(region, layout, time_slice, scrub) {
+        Ok(enclave) => enclave,
+        Err(sbi_ret) => return sbi_ret,
+    };
+    for segment in image.segments.iter() {
+        let start = base + (segment.offset & !(PAGE_SIZE - 1));
+        enclave
+            .memory
+            .lock()
+            .pin(start..base + segment.offset + segment.mem_size);
+    }
+    /* the host left anything in there, only the image may remain */
+    let range = region.addr_range();
+    let loaded = unsafe {
+        core::slice::from_raw_parts_mut(range.start as *mut u8, range.end - range.start)
+    };
+    image.load_into(loaded);
+    enclave.measurement.call_once(|| image.measure(loaded));
+
+    if let Some(handler) = image.trap_handler {
+        enclave.set_trap_policy(base + handler, manifest.trap_delegated as usize);
+    }
+    for tid in 0..manifest.threads as usize {
+        let sp = range.end - tid * manifest.stack_size as usize;
+        enclave.add_thread(base + image.entry, sp);
+    }
+    SbiRet::ok(enclave.id)
+}
@@ -0,0 +1,88 @@
+use alloc::vec::Vec;
+use spin::Mutex;
+
+use super::{get_enclave, measure::Measurement, Enclave};
+use crate::sbi::sbiret::SbiRet;
+
+pub const MESSAGE_SIZE: usize = 64;
+/* undelivered messages one enclave may have waiting */
+const MAILBOX_DEPTH: usize = 8;
+
+/* what a receive writes to the enclave buffer: sender, measurement, payload */
+#[repr(C)]
+struct Message {
+    from: usize,
+    measurement: Measurement,
+    data: [u8; MESSAGE_SIZE],
+}
+
+struct Envelope {
+    to: usize,
+    message: Message,
+}
+
+lazy_static::lazy_static! {
+    static ref MAILBOX: Mutex<Vec<Envelope>> = Mutex::new(Vec::new());
+}
+
+/* buffers passed by an enclave have to sit in its own memory */
+fn owns(enclave: &Enclave, addr: usize, len: usize) -> bool {
+    match addr.checked_add(len) {
+        Some(end) => enclave.memory.lock().contains_range(&(addr..end)),
+        None => false,
+    }
+}
+
+pub fn send(eid: usize, to: usize, buffer: usize) -> SbiRet {
+    let sender = match get_enclave(eid) {
+        Some(enclave) => enclave,
+        None => return SbiRet::invalid_param(),
+    };
+    if !owns(&sender, buffer, MESSAGE_SIZE) {
+        return SbiRet::invalid_address();
+    }
+    if to == eid || get_enclave(to).is_none() {
+        return SbiRet::invalid_param();
+    }
+    let measurement = match sender.measurement.get() {
+        Some(measurement) => *measurement,
+        None => return SbiRet::denied(),
+    };
+    let mut mailbox = MAILBOX.lock();
+    if mailbox.iter().filter(|e| e.to == to).count() >= MAILBOX_DEPTH {
+        return SbiRet::failed();
+    }
+    let mut data = [0u8; MESSAGE_SIZE];
+    unsafe { core::ptr::copy_nonoverlapping(buffer as *const u8, data.as_mut_ptr(), MESSAGE_SIZE) };
+    mailbox.push(Envelope {
+        to,
+        message: Message {
+            from: eid,
+            measurement,
+            data,
+        },
+    });
+    SbiRet::ok(0)
+}
+
+/* oldest message first, `None` when nothing is waiting */
+pub fn receive(eid: usize, buffer: usize) -> Option<SbiRet> {
+    let receiver = match get_enclave(eid) {
+        Some(enclave) => enclave,
+        None => return Some(SbiRet::invalid_param()),
+    };
+    if !owns(&receiver, buffer, core::mem::size_of::<Message>()) {
+        return Some(SbiRet::invalid_address());
+    }
+    let mut mailbox = MAILBOX.lock();
+    let idx = mailbox.iter().position(|e| e.to == eid)?;
+    let message = mailbox.remove(idx).message;
+    let from = message.from;
+    unsafe { core::ptr::write_unaligned(buffer as *mut Message, message) };
+    Some(SbiRet::ok(from))
+}
+
+/* called on enclave destroy, its id may be handed out again */
+pub(crate) fn drop_messages(eid: usize) {
+    MAILBOX.lock().retain(|e| e.to != eid);
+}
@@ -0,0 +1,10 @@
+pub use coffer_common::measure::{measure, Measurement};
+
+use crate::memory::memory_layout::Region;
+
+pub fn measure_region(region: &Region, entry: usize, config: &[u8]) -> Measurement {
+    let range = region.addr_range();
+    let image =
+        unsafe { core::slice::from_raw_parts(range.start as *const u8, range.end - range.start) };
+    measure(image, entry - range.start, config)
+}
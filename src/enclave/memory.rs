@@ -0,0 +1,310 @@
+use core::ops::Range;
+
+use alloc::vec::Vec;
+
+use super::{
+    claim_memory, get_enclave, overlaps_enclaves, shm::overlaps_shared, sync::sync_pmp_all,
+};
+use crate::{
+    memory::{
+        memory_layout::{coffer_range, MemoryLayout, Region, HOST_LAYOUT},
+        pmp::PmpFlags,
+        pool::{Owner, MEMORY_POOL, PAGE_SIZE},
+    },
+    sbi::sbiret::SbiRet,
+};
+
+/* one napot chunk owned by an enclave */
+pub struct PrivateRegion {
+    pub region: Region,
+    /* allow entry in the enclave layout */
+    enclave_slot: usize,
+    /* deny entry in HOST_LAYOUT */
+    host_slot: usize,
+}
+
+/* a chunk the enclave no longer maps, still denied to the host until it is scrubbed */
+pub struct Detached {
+    pub region: Region,
+    host_slot: usize,
+}
+
+impl Detached {
+    /* hand the chunk back to the host, only once every hart has dropped the enclave's
+     * access and the chunk is scrubbed */
+    pub fn release(self, host: &mut MemoryLayout) -> Region {
+        host.remove_region(self.host_slot);
+        self.region
+    }
+}
+
+pub struct EnclaveMemory {
+    regions: Vec<PrivateRegion>,
+    /* every chunk gets the permissions of the initial region, a loaded enclave's are RW */
+    access: PmpFlags,
+    /* loaded image, the segment pmp entries refer to it so it cannot be released */
+    pinned: Vec<Range<usize>>,
+}
+
+impl EnclaveMemory {
+    pub fn new(access: PmpFlags) -> Self {
+        EnclaveMemory {
+            regions: Vec::new(),
+            access,
+            pinned: Vec::new(),
+        }
+    }
+
+    pub fn access(&self) -> PmpFlags {
+        self.access
+    }
+
+    pub fn pin(&mut self, range: Range<usize>) {
+        self.pinned.push(range);
+    }
+
+    pub fn overlaps(&self, range: &Range<usize>) -> bool {
+        self.regions.iter().any(|r| r.region.overlaps(range))
+    }
+
+    pub fn contains(&self, addr: usize) -> bool {
+        self.regions
+            .iter()
+            .any(|r| r.region.addr_range().contains(&addr))
+    }
+
+    /* all of `range` inside one chunk */
+    pub fn contains_range(&self, range: &Range<usize>) -> bool {
+        self.regions.iter().any(|r| {
+            let region = r.region.addr_range();
+            region.start <= range.start && range.end <= region.end
+        })
+    }
+
+    fn map(
+        &mut self,
+        layout: &mut MemoryLayout,
+        host: &mut MemoryLayout,
+        region: Region,
+    ) -> Result<(), &'static str> {
+        let enclave_slot = layout.add_region(region)?;
+        let deny = Region {
+            pmp_cfg: PmpFlags::MODE_NAPOT,
+            ..region
+        };
+        let host_slot = match host.add_region(deny) {
+            Ok(idx) => idx,
+            Err(e) => {
+                layout.remove_region(enclave_slot);
+                return Err(e);
+            }
+        };
+        self.regions.push(PrivateRegion {
+            region,
+            enclave_slot,
+            host_slot,
+        });
+        Ok(())
+    }
+
+    fn unmap(&mut self, idx: usize, layout: &mut MemoryLayout, host: &mut MemoryLayout) -> Region {
+        self.detach(idx, layout).release(host)
+    }
+
+    /* the enclave loses the chunk, the host does not get it yet */
+    fn detach(&mut self, idx: usize, layout: &mut MemoryLayout) -> Detached {
+        let private = self.regions.remove(idx);
+        layout.remove_region(private.enclave_slot);
+        Detached {
+            region: private.region,
+            host_slot: private.host_slot,
+        }
+    }
+
+    /* map a chunk, merging it with its napot buddy to save pmp slots */
+    pub fn insert(
+        &mut self,
+        layout: &mut MemoryLayout,
+        host: &mut MemoryLayout,
+        region: Region,
+    ) -> Result<(), &'static str> {
+        let mut region = region;
+        loop {
+            let len = 1 << region.size;
+            let buddy = region.addr ^ len;
+            match self
+                .regions
+                .iter()
+                .position(|r| r.region.addr == buddy && r.region.size == region.size)
+            {
+                Some(idx) => {
+                    self.unmap(idx, layout, host);
+                    region = Region::napot(region.addr & !len, len << 1, self.access)?;
+                }
+                None => break,
+            }
+        }
+        self.map(layout, host, region)
+    }
+
+    /* detach `[addr, addr + len)`, splitting the chunk holding it if needed; on error
+     * nothing has changed */
+    pub fn remove(
+        &mut self,
+        layout: &mut MemoryLayout,
+        host: &mut MemoryLayout,
+        addr: usize,
+        len: usize,
+    ) -> Result<Detached, &'static str> {
+        let target = Region::napot(addr, len, self.access)?;
+        if self.pinned.iter().any(|pinned| target.overlaps(pinned)) {
+            return Err("[ERROR]: range holds the loaded image");
+        }
+        let idx = self
+            .regions
+            .iter()
+            .position(|r| {
+                let range = r.region.addr_range();
+                range.start <= addr && addr + len <= range.end
+            })
+            .ok_or("[ERROR]: range is not owned by the enclave")?;
+        let mut rest = Vec::new();
+        let mut region = self.regions[idx].region;
+        while region.size > target.size {
+            let half = 1 << (region.size - 1);
+            let lower = Region::napot(region.addr, half, self.access)?;
+            let upper = Region::napot(region.addr + half, half, self.access)?;
+            if addr < region.addr + half {
+                rest.push(upper);
+                region = lower;
+            } else {
+                rest.push(lower);
+                region = upper;
+            }
+        }
+        /* the split-off halves need one slot each on both sides, the chunk keeps its host
+         * slot until the target is released */
+        if rest.len() > layout.free_slots() || rest.len() > host.free_slots() {
+            return Err("[ERROR]: not enough pmp slots to split region");
+        }
+        for (mapped, piece) in rest.into_iter().enumerate() {
+            if let Err(e) = self.map(layout, host, piece) {
+                for _ in 0..mapped {
+                    let last = self.regions.len() - 1;
+                    self.unmap(last, layout, host);
+                }
+                return Err(e);
+            }
+        }
+        let mut detached = self.detach(idx, layout);
+        /* the host entry of the whole chunk stays, its other pieces have their own */
+        detached.region = target;
+        Ok(detached)
+    }
+
+    /* detach everything, the caller syncs, scrubs and then releases */
+    pub fn clear(&mut self, layout: &mut MemoryLayout) -> Vec<Detached> {
+        let mut detached = Vec::new();
+        while !self.regions.is_empty() {
+            detached.push(self.detach(0, layout));
+        }
+        detached
+    }
+}
+
+pub fn scrub_region(region: &Region) {
+    let range = region.addr_range();
+    unsafe { core::ptr::write_bytes(range.start as *mut u8, 0, range.end - range.start) };
+}
+
+pub(crate) fn overlaps_pool(range: &Range<usize>) -> bool {
+    MEMORY_POOL.lock().overlaps(range)
+}
+
+/* the host hands memory over to coffer, it stays host accessible until granted */
+pub fn donate(base: usize, size: usize) -> SbiRet {
+    let end = match base.checked_add(size) {
+        Some(end) => end,
+        None => return SbiRet::invalid_param(),
+    };
+    let range = base..end;
+    let coffer = coffer_range();
+    /* held until the pool has the range, see `claim_memory` */
+    let enclaves = claim_memory();
+    if (coffer.start < range.end && range.start < coffer.end)
+        || overlaps_enclaves(&enclaves, &range)
+        || overlaps_shared(&range)
+    {
+        return SbiRet::denied();
+    }
+    match MEMORY_POOL.lock().register(base, size) {
+        Ok(()) => SbiRet::ok(0),
+        Err(_) => SbiRet::invalid_param(),
+    }
+}
+
+/* only pages no enclave holds can be taken back */
+pub fn reclaim(base: usize, size: usize) -> SbiRet {
+    match base.checked_add(size) {
+        Some(end) if MEMORY_POOL.lock().reclaim(base..end) => SbiRet::ok(0),
+        Some(_) => SbiRet::denied(),
+        None => SbiRet::invalid_param(),
+    }
+}
+
+pub fn grow_enclave(eid: usize, len: usize) -> SbiRet {
+    if len < PAGE_SIZE || !len.is_power_of_two() {
+        return SbiRet::invalid_param();
+    }
+    let enclave = match get_enclave(eid) {
+        Some(enclave) => enclave,
+        None => return SbiRet::invalid_param(),
+    };
+    let access = enclave.memory.lock().access();
+    let region = match MEMORY_POOL.lock().alloc(len, Owner::Enclave(eid)) {
+        Some(addr) => Region::napot(addr, len, access).unwrap(),
+        None => return SbiRet::failed(),
+    };
+    {
+        let mut memory = enclave.memory.lock();
+        let mut layout = enclave.layout.lock();
+        let mut host = HOST_LAYOUT.lock();
+        if memory.insert(&mut layout, &mut host, region).is_err() {
+            MEMORY_POOL
+                .lock()
+                .free(region.addr_range(), Owner::Enclave(eid));
+            return SbiRet::failed();
+        }
+    }
+    sync_pmp_all();
+    SbiRet::ok(region.addr)
+}
+
+pub fn shrink_enclave(eid: usize, addr: usize, len: usize) -> SbiRet {
+    if len < PAGE_SIZE {
+        return SbiRet::invalid_param();
+    }
+    let enclave = match get_enclave(eid) {
+        Some(enclave) => enclave,
+        None => return SbiRet::invalid_param(),
+    };
+    let detached = {
+        let mut memory = enclave.memory.lock();
+        let mut layout = enclave.layout.lock();
+        let mut host = HOST_LAYOUT.lock();
+        match memory.remove(&mut layout, &mut host, addr, len) {
+            Ok(detached) => detached,
+            Err(_) => return SbiRet::invalid_address(),
+        }
+    };
+    /* no thread of the enclave may write after the scrub, nothing of it may reach the host */
+    sync_pmp_all();
+    scrub_region(&detached.region);
+    detached.release(&mut HOST_LAYOUT.lock());
+    /* pool pages go back to the pool, the creation region straight to the host */
+    MEMORY_POOL
+        .lock()
+        .free(addr..addr + len, Owner::Enclave(eid));
+    sync_pmp_all();
+    SbiRet::ok(0)
+}
@@ -0,0 +1,432 @@
+use core::{
+    ops::{Generator, GeneratorState, Range},
+    pin::Pin,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use riscv::register::{
+    mcause::{self, Exception, Interrupt, Trap},
+    mhartid,
+    mstatus::{FS, MPP},
+    mtval,
+};
+use spin::{Mutex, Once, RwLock, RwLockWriteGuard};
+
+use self::audit::{record, AuditEvent, NO_THREAD};
+use self::measure::{measure_region, Measurement};
+use self::memory::{scrub_region, EnclaveMemory};
+use self::scrub::{scrub_on_exit, ScrubFlags};
+use self::sync::{kick_enclave, mark_host, mark_running, serve_ipi, sync_host_pmp};
+use self::trap::{EnclaveFault, TrapPolicy, TrapState};
+
+use crate::{
+    ecall::handle_ecall_enclave,
+    memory::{
+        memory_layout::{coffer_range, MemoryLayout, Region, HOST_LAYOUT},
+        pmp::PmpFlags,
+        pool::MEMORY_POOL,
+    },
+    runtime::{context::Context, runtime::Runtime},
+    sbi::{
+        ipi::process_ipi,
+        sbiret::SbiRet,
+        timer::{read_mtime, restore_supervisor_timer, set_machine_timer},
+        EXT_COFFER,
+    },
+};
+
+pub mod attest;
+pub mod audit;
+pub mod loader;
+pub mod mailbox;
+pub mod measure;
+pub mod memory;
+pub mod scrub;
+pub mod shm;
+pub mod sync;
+pub mod trap;
+
+/* default time slice in mtime ticks before the enclave is preempted */
+pub const DEFAULT_TIME_SLICE: u64 = 1_000_000;
+
+/* a host service requested by the enclave */
+pub struct Ocall {
+    pub number: usize,
+    /* physical address of the shared buffer carrying the arguments */
+    pub buffer: usize,
+}
+
+pub enum EnclaveExit {
+    /* enclave called exit with a return value */
+    Exit(usize),
+    /* exception neither coffer nor the enclave handles */
+    Fault(EnclaveFault),
+    /* enclave waits for the host to serve an ocall */
+    Ocall(Ocall),
+    /* time slice expired or the host has an interrupt pending, resumable */
+    Interrupted,
+    /* blocked on an empty mailbox, retries the receive when entered again */
+    Waiting,
+}
+
+impl EnclaveExit {
+    /* (reason, payload0, payload1) reported to the host */
+    pub fn to_reg(&self) -> (usize, usize, usize) {
+        match self {
+            EnclaveExit::Exit(value) => (0, *value, 0),
+            EnclaveExit::Fault(fault) => (1, *fault as usize, 0),
+            EnclaveExit::Ocall(ocall) => (2, ocall.number, ocall.buffer),
+            EnclaveExit::Interrupted => (3, 0, 0),
+            EnclaveExit::Waiting => (4, 0, 0),
+        }
+    }
+}
+
+struct EnclaveThread {
+    /* set while the thread waits for an ocall result */
+    ocall_pending: AtomicBool,
+    /* hart of the last entry, a different one is logged as a migration */
+    last_hart: AtomicUsize,
+    runtime: Mutex<Runtime<EnclaveExit>>,
+}
+
+pub struct Enclave {
+    pub id: usize,
+    /* initial image as loaded, vouches for the enclave to its peers */
+    pub measurement: Once<Measurement>,
+    /* enclave-private physical memory, grows and shrinks at runtime */
+    pub memory: Mutex<EnclaveMemory>,
+    /* mtime ticks a thread may run per entry */
+    time_slice: u64,
+    /* applied on every exit to the host, from the measured manifest */
+    scrub: ScrubFlags,
+    /* shared by all threads, applied on every hart entering the enclave */
+    layout: Mutex<MemoryLayout>,
+    threads: RwLock<Vec<Arc<EnclaveThread>>>,
+    trap_policy: Arc<Mutex<TrapPolicy>>,
+    /* set once destroy started, threads leave at their next trap */
+    destroyed: Arc<AtomicBool>,
+}
+
+lazy_static::lazy_static! {
+    static ref ENCLAVES: RwLock<Vec<Option<Arc<Enclave>>>> = RwLock::new(Vec::new());
+}
+
+/* an enclave being destroyed is gone for every caller but the pmp sync */
+pub fn get_enclave(eid: usize) -> Option<Arc<Enclave>> {
+    enclave_slot(eid).filter(|enclave| !enclave.destroyed.load(Ordering::Acquire))
+}
+
+/* the slot keeps the eid taken until destroy is done with it */
+pub(crate) fn enclave_slot(eid: usize) -> Option<Arc<Enclave>> {
+    ENCLAVES.read().get(eid).and_then(|e| e.clone())
+}
+
+pub(crate) fn overlaps_private(range: &Range<usize>) -> bool {
+    overlaps_enclaves(&ENCLAVES.read(), range)
+}
+
+fn overlaps_enclaves(enclaves: &[Option<Arc<Enclave>>], range: &Range<usize>) -> bool {
+    enclaves
+        .iter()
+        .flatten()
+        .any(|enclave| enclave.memory.lock().overlaps(range))
+}
+
+/* enclave memory, shared channels and the pool are only claimed with this held, so two
+ * claims cannot both pass their overlap checks; lock order: this, enclave memory, enclave
+ * layout, channels, pool, HOST_LAYOUT */
+pub(crate) fn claim_memory() -> RwLockWriteGuard<'static, Vec<Option<Arc<Enclave>>>> {
+    ENCLAVES.write()
+}
+
+fn enclave_handler(
+    eid: usize,
+    destroyed: Arc<AtomicBool>,
+    mut trap: TrapState,
+) -> Box<dyn FnMut(*mut Context) -> Option<EnclaveExit> + Send> {
+    Box::new(move |ctx_ptr| unsafe {
+        let cause = mcause::read();
+        match cause.cause() {
+            Trap::Exception(Exception::UserEnvCall) if (*ctx_ptr).a7 != EXT_COFFER => {
+                if !trap.deliver(&mut *ctx_ptr, cause.code(), 0) {
+                    return Some(EnclaveExit::Fault(EnclaveFault::EnvCall));
+                }
+            }
+            Trap::Exception(Exception::UserEnvCall) => {
+                (*ctx_ptr).mepc = (*ctx_ptr).mepc + 4;
+                match handle_ecall_enclave(eid, ctx_ptr, &mut trap) {
+                    Ok(sbi_ret) => {
+                        (*ctx_ptr).a0 = sbi_ret.error;
+                        (*ctx_ptr).a1 = sbi_ret.value;
+                    }
+                    Err(exit) => return Some(exit),
+                }
+            }
+            Trap::Interrupt(Interrupt::MachineSoft) => {
+                process_ipi();
+                /* destroy kicks every hart still inside the enclave */
+                if destroyed.load(Ordering::Acquire) {
+                    return Some(EnclaveExit::Interrupted);
+                }
+            }
+            /* the slice expired, or an S-mode interrupt waits for the host */
+            Trap::Interrupt(_) => return Some(EnclaveExit::Interrupted),
+            Trap::Exception(exception) => {
+                if !trap.deliver(&mut *ctx_ptr, cause.code(), mtval::read()) {
+                    return Some(EnclaveExit::Fault(exception.into()));
+                }
+            }
+        }
+        None
+    })
+}
+
+pub fn create_enclave(base: usize, size: usize, entry: usize, time_slice: usize) -> SbiRet {
+    let region = match Region::napot(
+        base,
+        size,
+        PmpFlags::READABLE | PmpFlags::WRITABLE | PmpFlags::EXECUTABLE,
+    ) {
+        Ok(region) => region,
+        Err(_) => return SbiRet::invalid_param(),
+    };
+    let range = region.addr_range();
+    if !range.contains(&entry) {
+        return SbiRet::invalid_address();
+    }
+    let enclave = match register_enclave(region, MemoryLayout::new(), time_slice, ScrubFlags::all())
+    {
+        Ok(enclave) => enclave,
+        Err(sbi_ret) => return sbi_ret,
+    };
+    enclave
+        .measurement
+        .call_once(|| measure_region(&region, entry, &[]));
+    enclave.add_thread(entry, range.end);
+    SbiRet::ok(enclave.id)
+}
+
+/* claim `region` for a new enclave without threads, the host has lost access on return */
+fn register_enclave(
+    region: Region,
+    layout: MemoryLayout,
+    time_slice: usize,
+    scrub: ScrubFlags,
+) -> Result<Arc<Enclave>, SbiRet> {
+    let range = region.addr_range();
+    let mut enclaves = claim_memory();
+    if region.overlaps(&coffer_range())
+        || overlaps_enclaves(&enclaves, &range)
+        || shm::overlaps_shared(&range)
+        || memory::overlaps_pool(&range)
+    {
+        return Err(SbiRet::denied());
+    }
+
+    /* host loses every access to the enclave memory */
+    let mut layout = layout;
+    let mut memory = EnclaveMemory::new(region.pmp_cfg - PmpFlags::MODE_NAPOT);
+    if memory
+        .insert(&mut layout, &mut HOST_LAYOUT.lock(), region)
+        .is_err()
+    {
+        return Err(SbiRet::failed());
+    }
+
+    let eid = match enclaves.iter().position(|e| e.is_none()) {
+        Some(idx) => idx,
+        None => {
+            enclaves.push(None);
+            enclaves.len() - 1
+        }
+    };
+    let enclave = Arc::new(Enclave {
+        id: eid,
+        measurement: Once::new(),
+        memory: Mutex::new(memory),
+        time_slice: match time_slice {
+            0 => DEFAULT_TIME_SLICE,
+            ticks => ticks as u64,
+        },
+        scrub,
+        layout: Mutex::new(layout),
+        threads: RwLock::new(Vec::new()),
+        destroyed: Arc::new(AtomicBool::new(false)),
+        trap_policy: Arc::new(Mutex::new(TrapPolicy::none())),
+    });
+    enclaves[eid] = Some(enclave.clone());
+    drop(enclaves);
+    sync_host_pmp();
+    record(AuditEvent::Create, eid, NO_THREAD, 0);
+    Ok(enclave)
+}
+
+impl Enclave {
+    pub(crate) fn add_thread(&self, entry: usize, sp: usize) -> usize {
+        let mut threads = self.threads.write();
+        let tid = threads.len();
+        let mut ctx = Context::new();
+        ctx.a0 = self.id;
+        ctx.a1 = tid;
+        ctx.sp = sp;
+        ctx.mepc = entry;
+        ctx.mstatus.set_mpp(MPP::User);
+        ctx.mstatus.set_fs(FS::Initial);
+        /* layout lives in the enclave, threads enforce it on entry */
+        let handler = enclave_handler(
+            self.id,
+            self.destroyed.clone(),
+            TrapState::new(self.trap_policy.clone()),
+        );
+        let runtime = Runtime::new(ctx, None, handler);
+        threads.push(Arc::new(EnclaveThread {
+            ocall_pending: AtomicBool::new(false),
+            last_hart: AtomicUsize::new(usize::MAX),
+            runtime: Mutex::new(runtime),
+        }));
+        tid
+    }
+
+    pub(crate) fn set_trap_policy(&self, handler: usize, delegated: usize) {
+        *self.trap_policy.lock() = TrapPolicy { handler, delegated };
+    }
+
+    fn thread(&self, tid: usize) -> Option<Arc<EnclaveThread>> {
+        self.threads.read().get(tid).cloned()
+    }
+}
+
+pub fn create_thread(eid: usize, entry: usize, sp: usize) -> SbiRet {
+    let enclave = match get_enclave(eid) {
+        Some(enclave) => enclave,
+        None => return SbiRet::invalid_param(),
+    };
+    let memory = enclave.memory.lock();
+    /* sp may sit right past the top of a chunk */
+    if !memory.contains(entry) || !(memory.contains(sp) || memory.contains(sp.wrapping_sub(1))) {
+        return SbiRet::invalid_address();
+    }
+    drop(memory);
+    SbiRet::ok(enclave.add_thread(entry, sp))
+}
+
+/* `answer` is the result of the thread's pending ocall, a thread waiting for one is only
+ * entered with it */
+fn run_enclave(
+    enclave: &Enclave,
+    tid: usize,
+    thread: &EnclaveThread,
+    answer: Option<usize>,
+) -> Result<EnclaveExit, SbiRet> {
+    let exit = {
+        /* a thread runs on at most one hart at a time; no spinning for it either, that hart
+         * may be waiting for this one to acknowledge a pmp sync */
+        let mut runtime = thread.runtime.try_lock().ok_or(SbiRet::denied())?;
+        /* only changed with the runtime held, so it cannot flip before the thread is entered */
+        if thread.ocall_pending.load(Ordering::Acquire) != answer.is_some() {
+            return Err(SbiRet::denied());
+        }
+        if enclave.destroyed.load(Ordering::Acquire) {
+            return Ok(EnclaveExit::Interrupted);
+        }
+        /* without a timer the enclave would keep the hart for good */
+        if !set_machine_timer(read_mtime() + enclave.time_slice) {
+            return Err(SbiRet::not_supported());
+        }
+        if let Some(value) = answer {
+            let ctx = runtime.context_mut();
+            ctx.a0 = 0;
+            ctx.a1 = value;
+            thread.ocall_pending.store(false, Ordering::Release);
+        }
+        let hart = mhartid::read();
+        match thread.last_hart.swap(hart, Ordering::Relaxed) {
+            last if last != hart && last != usize::MAX => {
+                record(AuditEvent::Migrate, enclave.id, tid, last)
+            }
+            _ => {}
+        }
+        record(AuditEvent::Enter, enclave.id, tid, 0);
+        mark_running(enclave.id);
+        enclave.layout.lock().enforce();
+        let exit = match Pin::new(&mut *runtime).resume(()) {
+            GeneratorState::Yielded(exit) => exit,
+            GeneratorState::Complete(()) => unreachable!(),
+        };
+        scrub_on_exit(enclave.scrub);
+        restore_supervisor_timer();
+        mark_host();
+        HOST_LAYOUT.lock().enforce();
+        if let EnclaveExit::Ocall(_) = exit {
+            thread.ocall_pending.store(true, Ordering::Release);
+        }
+        exit
+    };
+    match &exit {
+        EnclaveExit::Fault(fault) => record(AuditEvent::Fault, enclave.id, tid, *fault as usize),
+        _ => record(AuditEvent::Exit, enclave.id, tid, exit.to_reg().0),
+    }
+    shm::raise_host_notification(enclave.id);
+    Ok(exit)
+}
+
+fn get_thread(eid: usize, tid: usize) -> Result<(Arc<Enclave>, Arc<EnclaveThread>), SbiRet> {
+    let enclave = get_enclave(eid).ok_or(SbiRet::invalid_param())?;
+    let thread = enclave.thread(tid).ok_or(SbiRet::invalid_param())?;
+    Ok((enclave, thread))
+}
+
+pub fn enter_enclave(eid: usize, tid: usize) -> Result<EnclaveExit, SbiRet> {
+    let (enclave, thread) = get_thread(eid, tid)?;
+    /* an ocall has to be answered through `return_ocall` */
+    run_enclave(&enclave, tid, &thread, None)
+}
+
+pub fn return_ocall(eid: usize, tid: usize, value: usize) -> Result<EnclaveExit, SbiRet> {
+    let (enclave, thread) = get_thread(eid, tid)?;
+    run_enclave(&enclave, tid, &thread, Some(value))
+}
+
+pub fn destroy_enclave(eid: usize) -> SbiRet {
+    let enclave = match get_enclave(eid) {
+        Some(enclave) => enclave,
+        None => return SbiRet::invalid_param(),
+    };
+    /* pull every thread out of the enclave before scrubbing; only one destroy gets past */
+    if enclave.destroyed.swap(true, Ordering::AcqRel) {
+        return SbiRet::invalid_param();
+    }
+    kick_enclave(eid);
+    let threads = enclave.threads.read();
+    let runtimes: Vec<_> = threads
+        .iter()
+        .map(|t| loop {
+            match t.runtime.try_lock() {
+                Some(runtime) => break runtime,
+                None => serve_ipi(),
+            }
+        })
+        .collect();
+    shm::revoke_channels(eid);
+    mailbox::drop_messages(eid);
+    /* every chunk the enclave grew into goes back to the host, zeroed once no hart can
+     * reach it through the enclave any more */
+    let detached = enclave.memory.lock().clear(&mut enclave.layout.lock());
+    drop(runtimes);
+    drop(threads);
+    sync_host_pmp();
+    for chunk in detached {
+        scrub_region(&chunk.region);
+        chunk.release(&mut HOST_LAYOUT.lock());
+    }
+    MEMORY_POOL.lock().free_all(eid);
+    sync_host_pmp();
+    /* no hart runs it or holds its pmp layout any more, the eid may be handed out again */
+    if let Some(slot) = ENCLAVES.write().get_mut(eid) {
+        *slot = None;
+    }
+    record(AuditEvent::Destroy, eid, NO_THREAD, 0);
+    SbiRet::ok(0)
+}
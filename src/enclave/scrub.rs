@@ -0,0 +1,71 @@
+use bitflags::*;
+use core::arch::asm;
+
+use crate::{
+    features::{has, Ext},
+    runtime::extension::{clear_fp, clear_vector, has_vector, invalidate_owner},
+};
+
+bitflags! {
+    /* residue cleared when a thread leaves the enclave; general registers need no flag, the
+     * host gets its own back from its context in full */
+    pub struct ScrubFlags: usize {
+        const FP =      1 << 0;
+        const VECTOR =  1 << 1;
+        const TLB =     1 << 2;
+        const BRANCH =  1 << 3;
+        const DCACHE =  1 << 4;
+    }
+}
+
+pub fn scrub_on_exit(flags: ScrubFlags) {
+    if flags.contains(ScrubFlags::FP) {
+        unsafe { scrub_fp() };
+    }
+    if flags.contains(ScrubFlags::VECTOR) && has_vector() {
+        unsafe { scrub_vector() };
+    }
+    if flags.contains(ScrubFlags::TLB) {
+        unsafe { riscv::asm::sfence_vma_all() };
+    }
+    if flags.contains(ScrubFlags::BRANCH) {
+        flush_branch_predictor();
+    }
+    if flags.contains(ScrubFlags::DCACHE) {
+        flush_dcache();
+    }
+}
+
+/* the owner's state was saved when it trapped, it is reloaded on next entry */
+unsafe fn scrub_fp() {
+    clear_fp();
+    invalidate_owner();
+}
+
+unsafe fn scrub_vector() {
+    clear_vector();
+    invalidate_owner();
+}
+
+/* T-Head mcor: BHT_INV and BTB_INV; the standard ISA has no way to reach the predictors */
+fn flush_branch_predictor() {
+    if has(Ext::XTheadCmo) {
+        unsafe { asm!("csrs 0x7c2, {bits}", bits = in(reg) (1 << 16) | (1 << 17)) };
+    }
+}
+
+/* dirty lines are written back before invalidation, the host may still own them */
+fn flush_dcache() {
+    if has(Ext::XTheadCmo) && has(Ext::XTheadSync) {
+        unsafe {
+            asm!(
+                "
+                /* dcache.ciall */
+                .word 0x0030000b
+                /* sync.is */
+                .word 0x01b0000b
+                "
+            )
+        };
+    }
+}
@@ -0,0 +1,177 @@
+use core::ops::Range;
+
+use alloc::vec::Vec;
+use spin::Mutex;
+
+use super::{
+    claim_memory, get_enclave, memory::overlaps_pool, overlaps_enclaves, sync::sync_enclave_pmp,
+};
+use crate::{
+    memory::{
+        memory_layout::{coffer_range, Region},
+        pmp::PmpFlags,
+    },
+    sbi::sbiret::SbiRet,
+};
+
+/* a buffer mapped RW by both the host and one enclave */
+pub struct SharedChannel {
+    pub id: usize,
+    pub eid: usize,
+    pub region: Region,
+    /* entry for `region` in the enclave layout */
+    enclave_slot: usize,
+    /* doorbells, set by one side and cleared when the other side polls */
+    host_pending: bool,
+    enclave_pending: bool,
+}
+
+lazy_static::lazy_static! {
+    static ref CHANNELS: Mutex<Vec<Option<SharedChannel>>> = Mutex::new(Vec::new());
+}
+
+pub(crate) fn overlaps_shared(range: &Range<usize>) -> bool {
+    CHANNELS
+        .lock()
+        .iter()
+        .flatten()
+        .any(|channel| channel.region.overlaps(range))
+}
+
+pub fn create_channel(eid: usize, base: usize, size: usize) -> SbiRet {
+    let region = match Region::napot(base, size, PmpFlags::READABLE | PmpFlags::WRITABLE) {
+        Ok(region) => region,
+        Err(_) => return SbiRet::invalid_param(),
+    };
+    let range = region.addr_range();
+    /* held until the channel is in place, no enclave can claim the range meanwhile */
+    let enclaves = claim_memory();
+    let enclave = match enclaves.get(eid).and_then(|e| e.clone()) {
+        Some(enclave) => enclave,
+        None => return SbiRet::invalid_param(),
+    };
+    /* neither coffer nor any enclave may give away private memory */
+    if region.overlaps(&coffer_range()) || overlaps_enclaves(&enclaves, &range) {
+        return SbiRet::denied();
+    }
+    let mut layout = enclave.layout.lock();
+    let mut channels = CHANNELS.lock();
+    if channels.iter().flatten().any(|c| c.region.overlaps(&range)) || overlaps_pool(&range) {
+        return SbiRet::denied();
+    }
+    let enclave_slot = match layout.add_region(region) {
+        Ok(idx) => idx,
+        Err(_) => return SbiRet::failed(),
+    };
+    let id = match channels.iter().position(|c| c.is_none()) {
+        Some(idx) => idx,
+        None => {
+            channels.push(None);
+            channels.len() - 1
+        }
+    };
+    channels[id] = Some(SharedChannel {
+        id,
+        eid,
+        region,
+        enclave_slot,
+        host_pending: false,
+        enclave_pending: false,
+    });
+    drop(channels);
+    drop(layout);
+    drop(enclaves);
+    sync_enclave_pmp(eid);
+    SbiRet::ok(id)
+}
+
+pub fn destroy_channel(id: usize) -> SbiRet {
+    let eid = match CHANNELS.lock().get(id) {
+        Some(Some(channel)) => channel.eid,
+        _ => return SbiRet::invalid_param(),
+    };
+    let enclave = get_enclave(eid);
+    let mut layout = enclave.as_ref().map(|enclave| enclave.layout.lock());
+    let channel = match CHANNELS.lock().get_mut(id).and_then(|c| c.take()) {
+        Some(channel) => channel,
+        None => return SbiRet::invalid_param(),
+    };
+    if let Some(layout) = layout.as_mut() {
+        layout.remove_region(channel.enclave_slot);
+    }
+    drop(layout);
+    sync_enclave_pmp(eid);
+    SbiRet::ok(0)
+}
+
+/* called on enclave destroy, the enclave layout goes away with it */
+pub(crate) fn revoke_channels(eid: usize) {
+    for slot in CHANNELS.lock().iter_mut() {
+        if slot.as_ref().map_or(false, |c| c.eid == eid) {
+            *slot = None;
+        }
+    }
+}
+
+fn with_channel<F>(id: usize, eid: Option<usize>, f: F) -> SbiRet
+where
+    F: FnOnce(&mut SharedChannel) -> SbiRet,
+{
+    match CHANNELS.lock().get_mut(id) {
+        /* an enclave may only touch its own channels */
+        Some(Some(channel)) if eid.map_or(true, |eid| eid == channel.eid) => f(channel),
+        _ => SbiRet::invalid_param(),
+    }
+}
+
+pub fn host_notify(id: usize) -> SbiRet {
+    with_channel(id, None, |channel| {
+        channel.enclave_pending = true;
+        SbiRet::ok(0)
+    })
+}
+
+pub fn host_poll(id: usize) -> SbiRet {
+    with_channel(id, None, |channel| {
+        let pending = channel.host_pending;
+        channel.host_pending = false;
+        SbiRet::ok(pending as usize)
+    })
+}
+
+pub fn enclave_notify(eid: usize, id: usize) -> SbiRet {
+    with_channel(id, Some(eid), |channel| {
+        channel.host_pending = true;
+        SbiRet::ok(0)
+    })
+}
+
+pub fn enclave_poll(eid: usize, id: usize) -> SbiRet {
+    with_channel(id, Some(eid), |channel| {
+        let pending = channel.enclave_pending;
+        channel.enclave_pending = false;
+        SbiRet::ok(pending as usize)
+    })
+}
+
+pub fn enclave_channel_addr(eid: usize, id: usize) -> SbiRet {
+    with_channel(id, Some(eid), |channel| SbiRet::ok(channel.region.addr))
+}
+
+pub fn enclave_channel_size(eid: usize, id: usize) -> SbiRet {
+    with_channel(id, Some(eid), |channel| {
+        SbiRet::ok(1 << channel.region.size)
+    })
+}
+
+/* the host learns about enclave notifications through a supervisor soft irq once back */
+pub(crate) fn raise_host_notification(eid: usize) {
+    let pending = CHANNELS
+        .lock()
+        .iter()
+        .flatten()
+        .any(|c| c.eid == eid && c.host_pending);
+    if pending {
+        unsafe { riscv::register::mip::set_ssoft() };
+    }
+}
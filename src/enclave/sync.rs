@@ -0,0 +1,166 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use alloc::vec::Vec;
+use riscv::register::mip;
+use spin::{Once, RwLock};
+
+use super::enclave_slot;
+use crate::{
+    memory::memory_layout::HOST_LAYOUT,
+    sbi::{
+        hart_table::{current_hart, hart_count, harts},
+        ipi::{process_ipi, send_ipi_one},
+        ipi_event::{create_ipi_event, IpiEvent, IpiEventOps},
+    },
+    util::fdt::XLEN,
+};
+
+const HOST: usize = usize::MAX;
+/* never ran the host or an enclave, it holds no pmp state worth syncing */
+const OFFLINE: usize = usize::MAX - 1;
+
+/* by hart table index */
+struct HartSync {
+    /* enclave the hart currently runs, `HOST` when the hart is in the host */
+    running: AtomicUsize,
+    /* pmp syncs asked of the hart, and the last of them it has applied */
+    requested: AtomicUsize,
+    applied: AtomicUsize,
+}
+
+static HART_SYNC: Once<Vec<HartSync>> = Once::new();
+
+fn hart_sync() -> &'static [HartSync] {
+    HART_SYNC.get().expect("enclave sync used before init")
+}
+
+lazy_static::lazy_static! {
+    static ref IPI_PMP_SYNC_EVENT: IpiEvent = IpiEvent {
+        name: "IPI_PMP_SYNC",
+        ops: IpiEventOps {
+            before: None,
+            process: process_pmp_sync,
+            after: None,
+        },
+    };
+    static ref IPI_ENCLAVE_KICK_EVENT: IpiEvent = IpiEvent {
+        name: "IPI_ENCLAVE_KICK",
+        ops: IpiEventOps {
+            before: None,
+            process: process_enclave_kick,
+            after: None,
+        },
+    };
+}
+
+pub static IPI_PMP_SYNC_EVENT_ID: RwLock<usize> = RwLock::new(XLEN);
+pub static IPI_ENCLAVE_KICK_EVENT_ID: RwLock<usize> = RwLock::new(XLEN);
+
+pub fn init_enclave_sync() {
+    HART_SYNC.call_once(|| {
+        (0..hart_count())
+            .map(|_| HartSync {
+                running: AtomicUsize::new(OFFLINE),
+                requested: AtomicUsize::new(0),
+                applied: AtomicUsize::new(0),
+            })
+            .collect()
+    });
+    *IPI_PMP_SYNC_EVENT_ID.write() = create_ipi_event(&IPI_PMP_SYNC_EVENT);
+    *IPI_ENCLAVE_KICK_EVENT_ID.write() = create_ipi_event(&IPI_ENCLAVE_KICK_EVENT);
+}
+
+pub(crate) fn mark_running(eid: usize) {
+    hart_sync()[current_hart()].running.store(eid, Ordering::Release);
+}
+
+/* also brings the hart online before it first enters the host */
+pub(crate) fn mark_host() {
+    hart_sync()[current_hart()].running.store(HOST, Ordering::Release);
+}
+
+/* hart table index and hart ID of the other harts that booted and run what `target` picks;
+ * hart IDs may be past XLEN, so no HartMask */
+fn other_harts(target: impl Fn(usize) -> bool) -> impl Iterator<Item = (usize, usize)> {
+    let this = current_hart();
+    harts()
+        .iter()
+        .zip(hart_sync())
+        .enumerate()
+        .filter(move |(index, (hart, sync))| {
+            let running = sync.running.load(Ordering::Acquire);
+            *index != this && hart.is_enabled() && running != OFFLINE && target(running)
+        })
+        .map(|(index, (hart, _))| (index, hart.hartid))
+}
+
+/* re-apply whichever layout this hart currently runs under */
+fn process_pmp_sync() {
+    let sync = &hart_sync()[current_hart()];
+    /* every request counted here was made after its layout change */
+    let requested = sync.requested.load(Ordering::Acquire);
+    match sync.running.load(Ordering::Acquire) {
+        HOST | OFFLINE => HOST_LAYOUT.lock().enforce(),
+        eid => {
+            if let Some(enclave) = enclave_slot(eid) {
+                enclave.layout.lock().enforce();
+            }
+        }
+    }
+    sync.applied.store(requested, Ordering::Release);
+}
+
+/* ask the online harts `target` picks, other than this one, to re-apply their layout, and
+ * wait until all of them have; incoming requests are served meanwhile, so two harts syncing
+ * each other cannot deadlock */
+fn sync_harts(target: impl Fn(usize) -> bool) {
+    let event_id = *IPI_PMP_SYNC_EVENT_ID.read();
+    let tickets: Vec<(usize, usize)> = other_harts(target)
+        .filter_map(|(index, hartid)| {
+            let ticket = hart_sync()[index].requested.fetch_add(1, Ordering::AcqRel) + 1;
+            /* a hart no ipi reaches is never waited for */
+            send_ipi_one(hartid, event_id).then(|| (index, ticket))
+        })
+        .collect();
+    for (index, ticket) in tickets {
+        while hart_sync()[index].applied.load(Ordering::Acquire) < ticket {
+            serve_ipi();
+        }
+    }
+}
+
+/* one round of a busy wait on something another hart holds; that hart may itself wait
+ * for this one to acknowledge a sync */
+pub(crate) fn serve_ipi() {
+    if mip::read().msoft() {
+        process_ipi();
+    }
+    core::hint::spin_loop();
+}
+
+/* nothing to do here, the trap itself sends the enclave back to coffer */
+fn process_enclave_kick() {}
+
+/* the syncs return once every other hart runs under the new layouts; they must be called
+ * without holding locks remote harts take: enclave, layout and runtime locks */
+pub(crate) fn sync_host_pmp() {
+    HOST_LAYOUT.lock().enforce();
+    sync_harts(|_| true);
+}
+
+/* for changes touching both sides, safe to call from inside an enclave trap */
+pub(crate) fn sync_pmp_all() {
+    process_pmp_sync();
+    sync_harts(|_| true);
+}
+
+pub(crate) fn sync_enclave_pmp(eid: usize) {
+    sync_harts(|running| running == eid);
+}
+
+pub(crate) fn kick_enclave(eid: usize) {
+    let event_id = *IPI_ENCLAVE_KICK_EVENT_ID.read();
+    for (_, hartid) in other_harts(|running| running == eid) {
+        send_ipi_one(hartid, event_id);
+    }
+}
@@ -0,0 +1,122 @@
+use alloc::sync::Arc;
+use bit_field::BitField;
+use riscv::register::mcause::Exception;
+use spin::Mutex;
+
+use crate::{runtime::context::Context, sbi::sbiret::SbiRet};
+
+/* which exceptions the enclave handles itself, shared by all threads */
+#[derive(Clone, Copy)]
+pub struct TrapPolicy {
+    pub handler: usize,
+    /* bit n set: exception code n goes to `handler` */
+    pub delegated: usize,
+}
+
+impl TrapPolicy {
+    pub const fn none() -> Self {
+        TrapPolicy {
+            handler: 0,
+            delegated: 0,
+        }
+    }
+}
+
+/* what the host learns about an enclave fault, no address or register leaves */
+#[repr(usize)]
+#[derive(Clone, Copy)]
+pub enum EnclaveFault {
+    Misaligned = 0,
+    AccessFault = 1,
+    IllegalInstruction = 2,
+    Breakpoint = 3,
+    PageFault = 4,
+    EnvCall = 5,
+    Unknown = 6,
+}
+
+impl From<Exception> for EnclaveFault {
+    fn from(exception: Exception) -> Self {
+        match exception {
+            Exception::InstructionMisaligned
+            | Exception::LoadMisaligned
+            | Exception::StoreMisaligned => EnclaveFault::Misaligned,
+            Exception::InstructionFault | Exception::LoadFault | Exception::StoreFault => {
+                EnclaveFault::AccessFault
+            }
+            Exception::IllegalInstruction => EnclaveFault::IllegalInstruction,
+            Exception::Breakpoint => EnclaveFault::Breakpoint,
+            Exception::InstructionPageFault
+            | Exception::LoadPageFault
+            | Exception::StorePageFault => EnclaveFault::PageFault,
+            Exception::UserEnvCall | Exception::SupervisorEnvCall | Exception::MachineEnvCall => {
+                EnclaveFault::EnvCall
+            }
+            Exception::Unknown => EnclaveFault::Unknown,
+        }
+    }
+}
+
+/* registers clobbered by a delivery, restored by trap return */
+struct TrapFrame {
+    epc: usize,
+    a0: usize,
+    a1: usize,
+    a2: usize,
+}
+
+/* per-thread delivery state, owned by the thread's exception handler */
+pub struct TrapState {
+    policy: Arc<Mutex<TrapPolicy>>,
+    frame: Option<TrapFrame>,
+}
+
+impl TrapState {
+    pub fn new(policy: Arc<Mutex<TrapPolicy>>) -> Self {
+        TrapState {
+            policy,
+            frame: None,
+        }
+    }
+
+    pub fn register(&mut self, handler: usize, delegated: usize) -> SbiRet {
+        *self.policy.lock() = TrapPolicy { handler, delegated };
+        SbiRet::ok(0)
+    }
+
+    /* enter the enclave handler with a0: cause, a1: tval, a2: epc */
+    pub fn deliver(&mut self, ctx: &mut Context, code: usize, tval: usize) -> bool {
+        let policy = *self.policy.lock();
+        /* a fault inside the handler itself cannot be delivered again */
+        if code >= 64 || !policy.delegated.get_bit(code) || self.frame.is_some() {
+            return false;
+        }
+        self.frame = Some(TrapFrame {
+            epc: ctx.mepc,
+            a0: ctx.a0,
+            a1: ctx.a1,
+            a2: ctx.a2,
+        });
+        ctx.a0 = code;
+        ctx.a1 = tval;
+        ctx.a2 = ctx.mepc;
+        ctx.mepc = policy.handler;
+        true
+    }
+
+    /* a0/a1 come back through the return pair, like legacy calls do */
+    pub fn trap_return(&mut self, ctx: &mut Context, epc: usize) -> SbiRet {
+        match self.frame.take() {
+            Some(frame) => {
+                /* zero resumes at the trapping instruction */
+                ctx.mepc = if epc == 0 { frame.epc } else { epc };
+                ctx.a2 = frame.a2;
+                SbiRet {
+                    error: frame.a0,
+                    value: frame.a1,
+                }
+            }
+            None => SbiRet::denied(),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use alloc::vec::Vec;
+use spin::Once;
+
+use crate::sbi::hart_table::{current_hart, hart_index, harts};
+
+/* the parser lives in coffer-common so it can be tested on the host */
+pub use coffer_common::isa::{parse_isa, Ext, FeatureSet};
+
+/* JEDEC id of T-Head in mvendorid */
+const THEAD_VENDOR_ID: usize = 0x5b7;
+
+/* one per hart table entry */
+static FEATURES: Once<Vec<FeatureSet>> = Once::new();
+
+/* riscv,isa-extensions and riscv,isa together; for the calling hart misa decides the letters.
+ * Trees for T-Head parts predate the xthead names, mvendorid fills them in for every hart */
+pub fn init_features() {
+    FEATURES.call_once(|| {
+        let boot = hart_index(riscv::register::mhartid::read());
+        let thead = riscv::register::mvendorid::read()
+            .map_or(false, |vendor| vendor.bits() == THEAD_VENDOR_ID);
+        harts()
+            .iter()
+            .enumerate()
+            .map(|(index, hart)| {
+                let mut features = hart
+                    .isa
+                    .as_deref()
+                    .and_then(parse_isa)
+                    .unwrap_or_default();
+                for name in hart.isa_extensions.iter() {
+                    features.insert_name(name);
+                }
+                if thead {
+                    features.insert(Ext::XTheadCmo);
+                    features.insert(Ext::XTheadSync);
+                }
+                let misa = riscv::register::misa::read().filter(|_| Some(index) == boot);
+                if let Some(misa) = misa {
+                    features.clear_letters();
+                    ('a'..='z')
+                        .filter(|c| misa.has_extension(c.to_ascii_uppercase()))
+                        .for_each(|c| features.insert_letter(c));
+                }
+                features
+            })
+            .collect()
+    });
+}
+
+/* features of the hart at `index` in the hart table */
+pub fn hart_features(index: usize) -> Option<&'static FeatureSet> {
+    FEATURES.get()?.get(index)
+}
+
+pub fn current_features() -> Option<&'static FeatureSet> {
+    FEATURES.get()?.get(current_hart())
+}
+
+/* whether the calling hart implements `ext`; misa alone answers before init */
+pub fn has(ext: Ext) -> bool {
+    match current_features() {
+        Some(features) => features.has(ext),
+        None if (ext as u8) < 26 => riscv::register::misa::read()
+            .map_or(false, |misa| misa.has_extension((b'A' + ext as u8) as char)),
+        None => false,
+    }
+}
@@ -1,10 +1,23 @@
 use core::ptr::{read_volatile, write_volatile};
 
-use crate::sbi::{hart_mask::HartMask, ipi::Ipi, sbiret::SbiRet, timer::Timer};
+use super::driver::{first_reg, nth_reg, Driver, Provider};
+use crate::{
+    fdt::node::FdtNode,
+    sbi::{
+        hart_mask::HartMask,
+        hart_table::hartid_limit,
+        ipi::{init_ipi, Ipi},
+        sbiret::SbiRet,
+        timer::{init_timer, Timer},
+    },
+};
+
 pub struct Clint {
     base: usize,
     mtimecmp_offset: usize,
     max_hartid: usize,
+    /* address of mtime, where the legacy clint keeps it unless told otherwise */
+    mtime: usize,
 }
 
 impl Clint {
@@ -13,9 +26,18 @@ impl Clint {
             base,
             mtimecmp_offset,
             max_hartid,
+            mtime: base + 0xbff8,
         }
     }
 
+    pub fn with_mtime(self, mtime: usize) -> Self {
+        Self { mtime, ..self }
+    }
+
+    pub fn mtime(&self) -> u64 {
+        unsafe { read_volatile(self.mtime as *const u64) }
+    }
+
     pub fn set_timer(&self, hartid: usize, wait_for: u64) {
         unsafe {
             let base = self.base as *mut u8;
@@ -61,4 +83,52 @@ impl Timer for Clint {
         let hartid = riscv::register::mhartid::read();
         self.set_timer(hartid, stime_value);
     }
+
+    #[inline]
+    fn mtime(&self) -> u64 {
+        self.mtime()
+    }
+}
+
+pub const DRIVER: Driver = Driver {
+    name: "clint",
+    compatible: &["riscv,clint0", "sifive,clint0"],
+    provides: &[Provider::Ipi, Provider::Timer],
+    probe,
+};
+
+/* the legacy clint is a single region, mtimecmp sits at 0x4000 in it */
+fn probe(node: &FdtNode) -> Result<(), &'static str> {
+    let base = first_reg(node)?;
+    let max_hartid = hartid_limit();
+    init_ipi(Clint::new(base, 0x4000, max_hartid));
+    init_timer(Clint::new(base, 0x4000, max_hartid));
+    Ok(())
+}
+
+/* ACLINT splits the clint in two, each part keeps the clint register layout */
+pub const ACLINT_MSWI: Driver = Driver {
+    name: "aclint-mswi",
+    compatible: &["riscv,aclint-mswi"],
+    provides: &[Provider::Ipi],
+    probe: probe_mswi,
+};
+
+pub const ACLINT_MTIMER: Driver = Driver {
+    name: "aclint-mtimer",
+    compatible: &["riscv,aclint-mtimer"],
+    provides: &[Provider::Timer],
+    probe: probe_mtimer,
+};
+
+fn probe_mswi(node: &FdtNode) -> Result<(), &'static str> {
+    init_ipi(Clint::new(first_reg(node)?, 0, hartid_limit()));
+    Ok(())
+}
+
+/* reg = <mtime>, <mtimecmp> */
+fn probe_mtimer(node: &FdtNode) -> Result<(), &'static str> {
+    let mtime = first_reg(node)?;
+    init_timer(Clint::new(nth_reg(node, 1)?, 0, hartid_limit()).with_mtime(mtime));
+    Ok(())
 }
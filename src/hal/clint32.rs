@@ -5,9 +5,17 @@ use core::{
 
 use bit_field::BitField;
 
+use super::driver::{first_reg, Driver, Provider};
 use crate::{
+    fdt::node::FdtNode,
     println,
-    sbi::{hart_mask::HartMask, ipi::Ipi, sbiret::SbiRet, timer::Timer},
+    sbi::{
+        hart_mask::HartMask,
+        hart_table::hartid_limit,
+        ipi::{init_ipi, Ipi},
+        sbiret::SbiRet,
+        timer::{init_timer, Timer},
+    },
 };
 pub struct Clint32 {
     base: usize,
@@ -68,9 +76,26 @@ impl Ipi for Clint32 {
     }
 }
 
+/* the C906 clint has no mtime register, the time CSR reads the counter it compares against */
 impl Timer for Clint32 {
     fn set_timer(&self, stime_value: u64) {
         let hartid = riscv::register::mhartid::read();
         self.set_timer(hartid, stime_value);
     }
 }
+
+/* the C906 clint only takes 32-bit accesses */
+pub const DRIVER: Driver = Driver {
+    name: "clint32",
+    compatible: &["thead,c900-clint", "allwinner,sun20i-d1-clint"],
+    provides: &[Provider::Ipi, Provider::Timer],
+    probe,
+};
+
+fn probe(node: &FdtNode) -> Result<(), &'static str> {
+    let base = first_reg(node)?;
+    let max_hartid = hartid_limit();
+    init_ipi(Clint32::new(base, 0x4000, max_hartid));
+    init_timer(Clint32::new(base, 0x4000, max_hartid));
+    Ok(())
+}
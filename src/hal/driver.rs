@@ -0,0 +1,94 @@
+use alloc::vec::Vec;
+
+use super::{clint, clint32, ns16550a, sifive_test, sifive_uart, sunxi_uart, tlb};
+use crate::{
+    debug,
+    fdt::{node::FdtNode, Fdt},
+    println,
+};
+
+/* the SBI services a driver can back */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Provider {
+    Console,
+    Ipi,
+    Timer,
+    Reset,
+    Fence,
+}
+
+pub struct Driver {
+    pub name: &'static str,
+    pub compatible: &'static [&'static str],
+    /* each provider is taken by the first driver probed for it */
+    pub provides: &'static [Provider],
+    pub probe: fn(&FdtNode) -> Result<(), &'static str>,
+}
+
+static DRIVERS: &[Driver] = &[
+    ns16550a::DRIVER,
+    sifive_uart::DRIVER,
+    sunxi_uart::DRIVER,
+    clint::DRIVER,
+    clint::ACLINT_MSWI,
+    clint::ACLINT_MTIMER,
+    clint32::DRIVER,
+    sifive_test::DRIVER,
+    tlb::DRIVER,
+];
+
+/* the base address of the first reg entry */
+pub fn first_reg(node: &FdtNode) -> Result<usize, &'static str> {
+    node.reg()
+        .and_then(|mut reg| reg.next())
+        .map(|reg| reg.address as usize)
+        .ok_or("[ERROR]: node has no reg")
+}
+
+/* the base address of the reg entry at `index` */
+pub fn nth_reg(node: &FdtNode, index: usize) -> Result<usize, &'static str> {
+    node.reg()
+        .and_then(|mut reg| reg.nth(index))
+        .map(|reg| reg.address as usize)
+        .ok_or("[ERROR]: node has too few reg entries")
+}
+
+/* "secure-okay" devices are coffer's, only "disabled" ones are skipped */
+fn enabled(node: &FdtNode) -> bool {
+    node.property("status")
+        .and_then(|prop| prop.as_str())
+        .map_or(true, |status| status != "disabled")
+}
+
+/* one pass over the tree, binding every provider to the first matching driver */
+pub fn probe_all(fdt: &Fdt) -> Vec<Provider> {
+    let mut bound = Vec::new();
+    for node in fdt.node_iter() {
+        if node.compatible().next().is_none() || !enabled(&node) {
+            continue;
+        }
+        let driver = match DRIVERS.iter().find(|d| node.is_compatible(d.compatible)) {
+            Some(driver) => driver,
+            None => {
+                debug!("no driver for {}", node.path());
+                continue;
+            }
+        };
+        if driver.provides.iter().all(|p| bound.contains(p)) {
+            debug!("{} already provided, skip {}", driver.name, node.path());
+            continue;
+        }
+        match (driver.probe)(&node) {
+            Ok(()) => {
+                for provider in driver.provides {
+                    if !bound.contains(provider) {
+                        bound.push(*provider);
+                    }
+                }
+                debug!("{} bound to {}", driver.name, node.path());
+            }
+            Err(e) => println!("{}: {} failed on {}", e, driver.name, node.path()),
+        }
+    }
+    bound
+}
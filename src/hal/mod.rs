@@ -1,12 +1,15 @@
 mod clint;
 mod clint32;
+pub mod driver;
 mod ns16550a;
+mod sifive_test;
 mod sifive_uart;
 mod sunxi_uart;
 mod tlb;
 pub use clint::Clint;
 pub use clint32::Clint32;
 pub use ns16550a::Ns16550a;
+pub use sifive_test::SifiveTest;
 pub use sifive_uart::SifiveUart;
 pub use sunxi_uart::SunxiUart;
 pub use tlb::Tlb;
@@ -1,6 +1,12 @@
 use embedded_hal::serial::{Read, Write};
 
-use crate::util::reg::{read_reg, write_reg};
+use super::driver::{first_reg, Driver, Provider};
+use crate::{
+    fdt::node::FdtNode,
+    sbi::init_console_embedded_serial,
+    util::reg::{read_reg, write_reg},
+};
+
 pub struct Ns16550a {
     base: usize,
     // TODO: make use of shift
@@ -91,3 +97,23 @@ impl Write<u8> for Ns16550a {
         }
     }
 }
+
+pub const DRIVER: Driver = Driver {
+    name: "ns16550a",
+    compatible: &["ns16550a", "ns16550"],
+    provides: &[Provider::Console],
+    probe,
+};
+
+fn probe(node: &FdtNode) -> Result<(), &'static str> {
+    let clock = node
+        .property("clock-frequency")
+        .and_then(|prop| prop.as_u64())
+        .ok_or("[ERROR]: ns16550a without clock-frequency")?;
+    let shift = node
+        .property("reg-shift")
+        .and_then(|prop| prop.as_usize())
+        .unwrap_or(0);
+    init_console_embedded_serial(Ns16550a::new(first_reg(node)?, shift, clock, 115200));
+    Ok(())
+}
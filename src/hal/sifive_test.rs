@@ -0,0 +1,50 @@
+use super::driver::{first_reg, Driver, Provider};
+use crate::{
+    fdt::node::FdtNode,
+    sbi::{
+        sbiret::SbiRet,
+        srst::{init_srst, ResetReason, ResetType, Srst},
+    },
+    util::reg::write_reg,
+};
+
+/* the test finisher QEMU virt uses for poweroff and reboot */
+pub struct SifiveTest {
+    base: usize,
+}
+
+mod value {
+    pub const FAIL: u32 = 0x3333;
+    pub const PASS: u32 = 0x5555;
+    pub const RESET: u32 = 0x7777;
+}
+
+impl SifiveTest {
+    pub fn new(base: usize) -> Self {
+        Self { base }
+    }
+}
+
+impl Srst for SifiveTest {
+    fn system_reset(&mut self, reset_type: ResetType, reset_reason: ResetReason) -> SbiRet {
+        let value = match (reset_type, reset_reason) {
+            (ResetType::Shutdown, ResetReason::NoReason) => value::PASS,
+            (ResetType::Shutdown, ResetReason::SystemFailure) => value::FAIL | (1 << 16),
+            (ResetType::ColdReboot, _) | (ResetType::WarmReboot, _) => value::RESET,
+        };
+        unsafe { write_reg::<u32>(self.base, 0, value) };
+        SbiRet::failed()
+    }
+}
+
+pub const DRIVER: Driver = Driver {
+    name: "sifive-test",
+    compatible: &["sifive,test1", "sifive,test0"],
+    provides: &[Provider::Reset],
+    probe,
+};
+
+fn probe(node: &FdtNode) -> Result<(), &'static str> {
+    init_srst(SifiveTest::new(first_reg(node)?));
+    Ok(())
+}
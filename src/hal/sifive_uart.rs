@@ -1,6 +1,11 @@
 use core::convert::Infallible;
 
-use crate::util::reg::{read_reg, write_reg};
+use super::driver::{first_reg, Driver, Provider};
+use crate::{
+    fdt::node::FdtNode,
+    sbi::init_console_embedded_serial,
+    util::reg::{read_reg, write_reg},
+};
 use embedded_hal::serial::{Read, Write};
 
 pub struct SifiveUart {
@@ -75,3 +80,16 @@ impl Write<u8> for SifiveUart {
         Ok(())
     }
 }
+
+pub const DRIVER: Driver = Driver {
+    name: "sifive-uart",
+    compatible: &["sifive,uart0"],
+    provides: &[Provider::Console],
+    probe,
+};
+
+/* the boot stage already set the divisor, keep it */
+fn probe(node: &FdtNode) -> Result<(), &'static str> {
+    init_console_embedded_serial(SifiveUart::new(first_reg(node)?, 0, 115200));
+    Ok(())
+}
@@ -1,6 +1,11 @@
 use core::convert::Infallible;
 
-use crate::util::reg::{read_reg, write_reg};
+use super::driver::{first_reg, Driver, Provider};
+use crate::{
+    fdt::node::FdtNode,
+    sbi::init_console_embedded_serial,
+    util::reg::{read_reg, write_reg},
+};
 use embedded_hal::serial::{Read, Write};
 
 pub struct SunxiUart {
@@ -57,3 +62,15 @@ impl Write<u8> for SunxiUart {
         }
     }
 }
+
+pub const DRIVER: Driver = Driver {
+    name: "sunxi-uart",
+    compatible: &["allwinner,sun20i-uart"],
+    provides: &[Provider::Console],
+    probe,
+};
+
+fn probe(node: &FdtNode) -> Result<(), &'static str> {
+    init_console_embedded_serial(SunxiUart::new(first_reg(node)?));
+    Ok(())
+}
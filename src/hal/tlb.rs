@@ -1,6 +1,10 @@
 use crate::sbi::fence_info::FenceInfo;
 use crate::sbi::rfence::LocalFence;
+use crate::sbi::rfence::init_rfence;
+use crate::fdt::node::FdtNode;
+use super::driver::{Driver, Provider};
 use core::arch::asm;
+
 pub struct Tlb;
 
 #[naked]
@@ -27,3 +31,16 @@ impl LocalFence for Tlb {
         todo!()
     }
 }
+
+/* plain sfence.vma, bound through any hart compatible with "riscv" */
+pub const DRIVER: Driver = Driver {
+    name: "tlb",
+    compatible: &["riscv"],
+    provides: &[Provider::Fence],
+    probe,
+};
+
+fn probe(_node: &FdtNode) -> Result<(), &'static str> {
+    init_rfence(Tlb);
+    Ok(())
+}
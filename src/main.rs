@@ -13,7 +13,8 @@
 extern crate alloc;
 
 mod ecall;
-mod fdt;
+mod enclave;
+mod features;
 mod hal;
 mod memory;
 mod platform;
@@ -23,6 +24,8 @@ mod util;
 #[macro_use]
 mod sbi;
 
+/* the parser lives in its own crate so it can be tested on the host */
+use coffer_fdt as fdt;
 use crate::{
     memory::pmp::PmpFlags,
     sbi::{ipi::process_ipi, timer::process_timer},
@@ -33,13 +36,14 @@ use ecall::handle_ecall;
 use platform::generic::generic_init;
 use riscv::{asm::wfi, register::{
     mcause::{self, Exception, Interrupt, Trap},
-    mstatus::MPP,
+    mstatus::{FS, MPP},
     stvec,
 }};
 use runtime::{context::Context, runtime::Runtime};
-use util::banner::print_banner;
+use util::{banner::print_banner, fdt::patch_dtb};
 use core::arch::asm;
-use crate::memory::memory_layout::Region;
+use crate::memory::memory_layout::{Region, HOST_LAYOUT};
+use crate::enclave::sync::mark_host;
 
 pub extern "C" fn main(hartid: usize, dtb: usize) -> ! {
     let hartid = riscv::register::mhartid::read();
@@ -55,6 +59,9 @@ pub extern "C" fn main(hartid: usize, dtb: usize) -> ! {
     global_region.enforce(0);
     if hartid == 0 {
         let jump_addr = generic_init(dtb);
+        let dtb = patch_dtb(dtb);
+        HOST_LAYOUT.lock().enforce();
+        mark_host();
         let mut rt = kernel_runtime(hartid, dtb, jump_addr);
         Pin::new(&mut rt).resume(());
     }
@@ -67,7 +74,7 @@ fn kernel_runtime(hartid: usize, dtb: usize, kernel_addr: usize) -> Runtime<()>
     ctx.a1 = dtb;
     ctx.mepc = kernel_addr;
     ctx.mstatus.set_mpp(MPP::Supervisor);
-    ctx.mstatus.set_fs(riscv::register::sstatus::FS::Dirty);
+    ctx.mstatus.set_fs(FS::Initial);
     ctx.mcounteren = 0xffff_ffff;
     //ctx.medeleg = 0xb1ff;
     //ctx.mideleg = 0x222;
@@ -1,12 +1,17 @@
 use core::ops::Range;
 
+use alloc::vec::Vec;
 use bit_field::BitField;
 use riscv::register::pmpaddr0;
+use spin::Mutex;
 
 use super::pmp::{pmpaddr_write, pmpcfg_write, PmpFlags};
 use crate::{println, util::fdt::XLEN};
 
+pub const PMP_COUNT: usize = 16;
+
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct Region {
     /* one protected region */
     pub addr: usize,
@@ -16,6 +21,31 @@ pub struct Region {
 }
 
 impl Region {
+    pub const fn disabled() -> Self {
+        Region {
+            addr: 0,
+            size: 0,
+            enabled: false,
+            pmp_cfg: PmpFlags::empty(),
+        }
+    }
+
+    /* `len` must be a power of two and `addr` aligned to it */
+    pub fn napot(addr: usize, len: usize, pmp_cfg: PmpFlags) -> Result<Self, &'static str> {
+        if len < 8 || !len.is_power_of_two() {
+            return Err("[ERROR]: napot region size is not a power of two");
+        }
+        if addr & (len - 1) != 0 {
+            return Err("[ERROR]: napot region is not aligned to its size");
+        }
+        Ok(Region {
+            addr,
+            size: len.trailing_zeros() as usize,
+            enabled: true,
+            pmp_cfg: pmp_cfg | PmpFlags::MODE_NAPOT,
+        })
+    }
+
     pub fn addr_range(&self) -> Range<usize> {
         if self.pmp_cfg.contains(PmpFlags::MODE_NA4) || self.pmp_cfg.contains(PmpFlags::MODE_NAPOT)
         {
@@ -31,6 +61,11 @@ impl Region {
         }
     }
 
+    pub fn overlaps(&self, other: &Range<usize>) -> bool {
+        let range = self.addr_range();
+        range.start < other.end && other.start < range.end
+    }
+
     fn to_napot(&self) -> usize {
         if self.size < 2 || self.size > 56 {
             panic!("[ERROR] invalid pmp napot value");
@@ -73,14 +108,73 @@ impl Region {
 
 pub struct MemoryLayout {
     /* at most 16 pmp region is allowed */
-    regions: [Region; 16],
+    regions: [Region; PMP_COUNT],
 }
 
 impl MemoryLayout {
+    pub const fn new() -> Self {
+        MemoryLayout {
+            regions: [Region::disabled(); PMP_COUNT],
+        }
+    }
+
+    /* host may access everything except what is explicitly denied in front of the last slot,
+     * coffer's own image first of all */
+    pub fn host() -> Self {
+        let mut layout = MemoryLayout::new();
+        layout.regions[PMP_COUNT - 1] = Region {
+            addr: 0x0,
+            size: 56,
+            enabled: true,
+            pmp_cfg: PmpFlags::EXECUTABLE
+                | PmpFlags::READABLE
+                | PmpFlags::WRITABLE
+                | PmpFlags::MODE_NAPOT,
+        };
+        /* the rest of coffer_range() holds the dtb and the payload, S-mode needs both */
+        for block in napot_blocks(image_range()) {
+            let deny = Region::napot(block.start, block.end - block.start, PmpFlags::empty())
+                .and_then(|region| layout.add_region(region));
+            if let Err(e) = deny {
+                panic!("{}: coffer image {:#x?} left open to S-mode", e, block);
+            }
+        }
+        layout
+    }
+
+    /* lower index has higher priority, so take the first free slot */
+    pub fn add_region(&mut self, region: Region) -> Result<usize, &'static str> {
+        match self.regions.iter_mut().enumerate().find(|(_, r)| !r.enabled) {
+            Some((idx, slot)) => {
+                *slot = region;
+                Ok(idx)
+            }
+            None => Err("[ERROR]: no free pmp slot in memory layout"),
+        }
+    }
+
+    pub fn free_slots(&self) -> usize {
+        self.regions.iter().filter(|r| !r.enabled).count()
+    }
+
+    pub fn remove_region(&mut self, index: usize) {
+        self.regions[index] = Region::disabled();
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item = &Region> {
+        self.regions.iter().filter(|r| r.enabled)
+    }
+
     pub fn enforce(&self) {
         for (i, region) in self.regions.iter().enumerate() {
-            region.enforce(i);
+            if region.enabled {
+                region.enforce(i);
+            } else {
+                region.exempt(i);
+            }
         }
+        /* pmp changes only take effect after a fence */
+        unsafe { riscv::asm::sfence_vma_all() };
     }
 
     pub fn exempt(&self) {
@@ -89,3 +183,46 @@ impl MemoryLayout {
         }
     }
 }
+
+/* the largest naturally aligned blocks covering `range` */
+pub fn napot_blocks(range: Range<usize>) -> Vec<Range<usize>> {
+    let mut blocks = Vec::new();
+    let mut addr = range.start;
+    while addr < range.end {
+        let mut len = 1 << addr.trailing_zeros().min(usize::BITS - 1);
+        while addr + len > range.end {
+            len >>= 1;
+        }
+        blocks.push(addr..addr + len);
+        addr += len;
+    }
+    blocks
+}
+
+lazy_static::lazy_static! {
+    pub static ref HOST_LAYOUT: Mutex<MemoryLayout> = Mutex::new(MemoryLayout::host());
+}
+
+/* physical range occupied by coffer itself, see `linkscript` */
+pub fn coffer_range() -> Range<usize> {
+    extern "C" {
+        fn _stext();
+        fn _coffer_end();
+    }
+    Range {
+        start: _stext as usize,
+        end: _coffer_end as usize,
+    }
+}
+
+/* what coffer's image really spans, up to the device tree buffer handed to S-mode */
+pub fn image_range() -> Range<usize> {
+    extern "C" {
+        fn _stext();
+        fn _dtb_start();
+    }
+    Range {
+        start: _stext as usize,
+        end: _dtb_start as usize,
+    }
+}
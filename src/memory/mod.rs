@@ -1,2 +1,3 @@
 pub mod memory_layout;
 pub mod pmp;
+pub mod pool;
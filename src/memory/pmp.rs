@@ -35,7 +35,7 @@ pub(crate) fn pmpcfg_write(index: usize, value: u8) {
         }
         8..=15 => {
             let range = (index - 8) * 8..(index - 7) * 8;
-            let mut reg_value = pmpcfg0::read();
+            let mut reg_value = pmpcfg2::read();
             reg_value.set_bits(range, value as usize);
             pmpcfg2::write(reg_value);
         }
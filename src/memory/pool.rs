@@ -0,0 +1,5 @@
+use spin::Mutex;
+
+pub use coffer_common::pool::{MemoryPool, Owner, PAGE_SIZE};
+
+pub static MEMORY_POOL: Mutex<MemoryPool> = Mutex::new(MemoryPool::new());
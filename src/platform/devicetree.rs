@@ -0,0 +1,74 @@
+use core::{ops::Range, ptr::write_volatile};
+
+use crate::{
+    fdt::Fdt,
+    println,
+    sbi::hart_table::{hart_count, harts},
+    util::fdt::{init_fdt, init_sunxi_clint, memory_ranges, probe_drivers, FDT},
+};
+
+/* where the payload sits above the start of memory when /chosen does not say */
+const DEFAULT_PAYLOAD_OFFSET: usize = 0x20_0000;
+
+/* what the tree alone does not tell about a board, keyed on the root compatible */
+struct Quirk {
+    compatible: &'static [&'static str],
+    payload_offset: usize,
+    fixup: fn(),
+}
+
+static QUIRKS: &[Quirk] = &[Quirk {
+    compatible: &["allwinner,d1"],
+    payload_offset: 0x200_0000,
+    fixup: sun20i_d1_fixup,
+}];
+
+/* the D1 tree carries no clint node, and S-mode needs the PLIC control bit */
+fn sun20i_d1_fixup() {
+    init_sunxi_clint(0x1400_0000);
+    unsafe { write_volatile(0x101F_FFFC as *mut u32, 0x1) };
+}
+
+/* /chosen/coffer,payload wins, else the quirk's or the default offset into memory */
+fn payload_addr(fdt: &Fdt, memory: &[Range<usize>], offset: usize) -> Option<usize> {
+    let chosen = fdt
+        .find_node("/chosen")
+        .and_then(|node| node.property("coffer,payload"))
+        .and_then(|prop| prop.as_usize());
+    chosen.or_else(|| memory.iter().map(|r| r.start).min().map(|base| base + offset))
+}
+
+/* any board whose device tree describes it, no cargo feature needed */
+pub fn devicetree_init(dtb: usize) -> usize {
+    if let Err(e) = init_fdt(dtb) {
+        panic!("{}", e);
+    }
+    probe_drivers();
+
+    let (payload, quirk) = {
+        let fdt = FDT.lock();
+        let fdt = fdt.as_ref().unwrap();
+        let root = fdt.root().expect("device tree without root");
+        let quirk = QUIRKS.iter().find(|quirk| root.is_compatible(quirk.compatible));
+        let model = root
+            .property("model")
+            .and_then(|prop| prop.as_str())
+            .or_else(|| root.compatible().next())
+            .unwrap_or("unknown");
+        let memory = memory_ranges(fdt);
+        let enabled = harts().iter().filter(|hart| hart.is_enabled()).count();
+        println!("Platform: {}, {} harts, {} enabled", model, hart_count(), enabled);
+        for range in memory.iter() {
+            println!("Memory: {:#x}..{:#x}", range.start, range.end);
+        }
+        let offset = quirk.map_or(DEFAULT_PAYLOAD_OFFSET, |quirk| quirk.payload_offset);
+        let payload = payload_addr(fdt, &memory, offset).expect("no memory in the device tree");
+        (payload, quirk)
+    };
+    /* the FDT lock is released, fixups may probe again */
+    if let Some(quirk) = quirk {
+        (quirk.fixup)();
+    }
+    println!("Payload: {:#x}", payload);
+    payload
+}
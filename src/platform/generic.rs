@@ -2,7 +2,10 @@ use core::panic::PanicInfo;
 
 use crate::main;
 use crate::println;
+use crate::enclave::sync::init_enclave_sync;
+use crate::features::init_features;
 use crate::sbi::hart_scratch::init_hart_scratch;
+use crate::util::fdt::protect_secure_devices;
 use core::arch::asm;
 use buddy_system_allocator::LockedHeap;
 
@@ -14,7 +17,8 @@ const SBI_STACK_SIZE: usize = NUM_CORES * HART_STACK_SIZE;
 #[link_section = ".bss.uninit"]
 static mut SBI_STACK: [u8; SBI_STACK_SIZE] = [0; SBI_STACK_SIZE];
 
-const SBI_HEAP_SIZE: usize = 128 * 1024;
+/* the device tree is copied into the heap while coffer patches it */
+const SBI_HEAP_SIZE: usize = 256 * 1024;
 
 #[no_mangle]
 #[link_section = ".bss.uninit"]
@@ -67,9 +71,13 @@ pub fn generic_init(dtb: usize) -> usize {
         () => crate::platform::virt::virt_init(dtb),
         #[cfg(feature = "sifive")]
         () => crate::platform::sifive::sifive_init(dtb),
-        _ => unreachable!(),
+        #[cfg(not(any(feature = "sunxi", feature = "virt", feature = "sifive")))]
+        () => crate::platform::devicetree::devicetree_init(dtb),
     };
+    init_features();
+    protect_secure_devices();
     init_hart_scratch();
+    init_enclave_sync();
     jump_addr
 }
 
@@ -1,3 +1,4 @@
+pub mod devicetree;
 pub mod generic;
 pub mod sifive;
 pub mod sunxi;
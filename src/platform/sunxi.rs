@@ -1,8 +1,8 @@
-use core::ptr::write_volatile;
+use core::ptr::{read_volatile, write_volatile};
 
 use crate::{
     println,
-    util::fdt::{detect_sunxi_uart, init_fdt, init_sunxi_clint},
+    util::fdt::{init_fdt, init_fdt_with_overlays, init_sunxi_clint, probe_drivers, Aligned},
 };
 
 #[repr(C)]
@@ -11,7 +11,8 @@ struct SunxiHead {
     pub magic: [u8; 8],
     pub dtb_base: u32,
     pub uboot_base: u32,
-    pub res3: u32,
+    /* a device tree overlay the boot stage left in memory, 0 for none */
+    pub overlay_base: u32,
     pub res4: u32,
     pub res5: [u8; 8],
     pub res6: [u8; 8],
@@ -25,19 +26,29 @@ static SUNXI_HEAD: SunxiHead = SunxiHead {
     magic: *b"opensbi\0",
     uboot_base: 0,
     dtb_base: 0,
-    res3: 0,
+    overlay_base: 0,
     res4: 0,
     res5: [0; 8],
     res6: [0; 8],
     opensbi_base: 0x4000_0000,
 };
 #[cfg(feature="sunxi")]
-static DEVICE_TREE: &[u8] = include_bytes!("../../dtb/sunxi.dtb");
+static DEVICE_TREE: &[u8] = &Aligned(*include_bytes!("../../dtb/sunxi.dtb")).0;
 
 #[cfg(feature="sunxi")]
 pub fn sunxi_init(dtb: usize) -> usize {
-    init_fdt(DEVICE_TREE.as_ptr() as usize);
-    detect_sunxi_uart();
+    let overlay = unsafe { read_volatile(&SUNXI_HEAD.overlay_base) } as usize;
+    let overlays = [overlay];
+    let overlays = if overlay != 0 { &overlays[..] } else { &[] };
+    /* a tree that cannot take the overlays still boots without them */
+    if let Err(e) = init_fdt_with_overlays(DEVICE_TREE.as_ptr() as usize, overlays) {
+        println!("{}: booting the base device tree", e);
+        if let Err(e) = init_fdt(DEVICE_TREE.as_ptr() as usize) {
+            println!("{}", e);
+        }
+    }
+    probe_drivers();
+    /* the D1 tree carries no clint node */
     init_sunxi_clint(0x1400_0000);
     // TODO: SETUP PLIC
     unsafe { write_volatile(0x101F_FFFC as *mut u32, 0x1) };
@@ -1,4 +1,8 @@
-use riscv::register::mstatus::Mstatus;
+use bit_field::BitField;
+use core::ops::Range;
+use riscv::register::mstatus::{FS, MPP};
+
+use super::extension::{FpState, VectorState};
 use core::arch::asm;
 
 #[repr(C)]
@@ -42,6 +46,63 @@ pub struct Context {
     pub mideleg: usize,
     pub medeleg: usize,
     pub mcounteren: usize,
+
+    pub supervisor: SupervisorCsrs, // x37
+    /* csrs of whoever resumed this runtime, parked while it runs */
+    pub host_supervisor: SupervisorCsrs, // x45
+    /* nonzero: switch the supervisor csrs too, off for the kernel */
+    pub switch_supervisor: usize, // x53
+
+    /* switched in Rust, the naked routines never touch these */
+    pub float: FpState,
+    pub vector: VectorState,
+    /* hart whose registers last received `float` and `vector` */
+    pub live_on: usize,
+}
+
+/* mstatus as the trap code saves and loads it, one plain word; the riscv crate's type
+ * makes no layout promise and has no access to VS */
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+pub struct Mstatus(usize);
+
+impl Mstatus {
+    pub fn bits(&self) -> usize {
+        self.0
+    }
+
+    pub fn field(&self, field: Range<usize>) -> usize {
+        self.0.get_bits(field)
+    }
+
+    pub fn set_field(&mut self, field: Range<usize>, value: usize) {
+        self.0.set_bits(field, value);
+    }
+
+    pub fn set_mpp(&mut self, mpp: MPP) {
+        self.set_field(11..13, mpp as usize);
+    }
+
+    pub fn set_mpie(&mut self, mpie: bool) {
+        self.0.set_bit(7, mpie);
+    }
+
+    pub fn set_fs(&mut self, fs: FS) {
+        self.set_field(13..15, fs as usize);
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SupervisorCsrs {
+    pub satp: usize,
+    pub stvec: usize,
+    pub sscratch: usize,
+    pub sepc: usize,
+    pub scause: usize,
+    pub stval: usize,
+    pub sie: usize,
+    pub sstatus: usize,
 }
 
 impl Context {
@@ -102,6 +163,43 @@ pub(super) unsafe extern "C" fn to_user_or_supervisor(context: *mut Context) {
         "
         sd      sp,         33*8(a0)
         csrw    mscratch,   a0
+        ld      t0,         53*8(a0)
+        beqz    t0,         1f
+        /* park the caller's supervisor csrs, sstatus goes before mstatus so mstatus wins */
+        csrr    t0,         satp
+        sd      t0,         45*8(a0)
+        csrr    t0,         stvec
+        sd      t0,         46*8(a0)
+        csrr    t0,         sscratch
+        sd      t0,         47*8(a0)
+        csrr    t0,         sepc
+        sd      t0,         48*8(a0)
+        csrr    t0,         scause
+        sd      t0,         49*8(a0)
+        csrr    t0,         stval
+        sd      t0,         50*8(a0)
+        csrr    t0,         sie
+        sd      t0,         51*8(a0)
+        csrr    t0,         sstatus
+        sd      t0,         52*8(a0)
+        ld      t0,         37*8(a0)
+        csrw    satp       ,t0
+        ld      t0,         38*8(a0)
+        csrw    stvec      ,t0
+        ld      t0,         39*8(a0)
+        csrw    sscratch   ,t0
+        ld      t0,         40*8(a0)
+        csrw    sepc       ,t0
+        ld      t0,         41*8(a0)
+        csrw    scause     ,t0
+        ld      t0,         42*8(a0)
+        csrw    stval      ,t0
+        ld      t0,         43*8(a0)
+        csrw    sie        ,t0
+        ld      t0,         44*8(a0)
+        csrw    sstatus    ,t0
+        sfence.vma
+1:
         /* TODO: uboot assumes all register is cleared */
         ld      t0,         31*8(a0)
         ld      t1,         32*8(a0)
@@ -209,7 +307,45 @@ pub(crate) unsafe extern "C" fn from_user_or_supervisor() {
          sd      t2,         34*8(a0)
          sd      t3,         35*8(a0)
          sd      t4,         36*8(a0)
-         
+
+         ld      t0,         53*8(a0)
+         beqz    t0,         1f
+         /* keep this runtime's supervisor csrs, give the caller its own back */
+         csrr    t0,         satp
+         sd      t0,         37*8(a0)
+         csrr    t0,         stvec
+         sd      t0,         38*8(a0)
+         csrr    t0,         sscratch
+         sd      t0,         39*8(a0)
+         csrr    t0,         sepc
+         sd      t0,         40*8(a0)
+         csrr    t0,         scause
+         sd      t0,         41*8(a0)
+         csrr    t0,         stval
+         sd      t0,         42*8(a0)
+         csrr    t0,         sie
+         sd      t0,         43*8(a0)
+         csrr    t0,         sstatus
+         sd      t0,         44*8(a0)
+         ld      t0,         45*8(a0)
+         csrw    satp       ,t0
+         ld      t0,         46*8(a0)
+         csrw    stvec      ,t0
+         ld      t0,         47*8(a0)
+         csrw    sscratch   ,t0
+         ld      t0,         48*8(a0)
+         csrw    sepc       ,t0
+         ld      t0,         49*8(a0)
+         csrw    scause     ,t0
+         ld      t0,         50*8(a0)
+         csrw    stval      ,t0
+         ld      t0,         51*8(a0)
+         csrw    sie        ,t0
+         ld      t0,         52*8(a0)
+         csrw    sstatus    ,t0
+         sfence.vma
+ 1:
+
          /* mscratch = a0;
           * t1 = mscratch;
           */
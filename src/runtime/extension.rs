@@ -0,0 +1,354 @@
+use alloc::{boxed::Box, vec};
+use bit_field::BitField;
+use core::arch::asm;
+use core::ops::Range;
+use riscv::register::mhartid;
+
+use super::context::Context;
+use coffer_common::lazy::{on_entry, save_on_exit, OnEntry, UnitState};
+use crate::features::{self, Ext};
+use crate::sbi::hart_scratch::get_hart_scratch;
+
+const MSTATUS_FS: Range<usize> = 13..15;
+/* XuanTie vector 0.7.1 keeps VS at [24:23] instead of [10:9] */
+#[cfg(feature = "sunxi")]
+pub const MSTATUS_VS: Range<usize> = 23..25;
+#[cfg(not(feature = "sunxi"))]
+pub const MSTATUS_VS: Range<usize> = 9..11;
+
+/* nobody's state is live in the registers */
+pub const NO_OWNER: usize = 0;
+
+#[repr(C)]
+pub struct FpState {
+    pub f: [u64; 32],
+    pub fcsr: usize,
+}
+
+#[repr(C)]
+pub struct VectorState {
+    pub vl: usize,
+    pub vtype: usize,
+    pub vstart: usize,
+    /* v0-v31 back to back, allocated on the first save */
+    pub regs: Option<Box<[u8]>>,
+}
+
+pub fn has_vector() -> bool {
+    features::has(Ext::V)
+}
+
+/* vector 0.7.1 has no vlenb, the C906 implements VLEN = 128 */
+#[cfg(feature = "sunxi")]
+fn vlenb() -> usize {
+    16
+}
+
+#[cfg(not(feature = "sunxi"))]
+fn vlenb() -> usize {
+    let vlenb: usize;
+    unsafe { asm!("csrr {}, 0xc22", out(reg) vlenb) };
+    vlenb
+}
+
+/* make the live FP/vector registers `ctx`'s where it may use them; a runtime with FS/VS Off,
+ * the kernel between FP uses for one, costs nothing */
+pub fn switch_in(id: usize, ctx: &mut Context) {
+    let hartid = mhartid::read();
+    let mut scratch = get_hart_scratch(hartid).lock();
+    /* the registers here are stale if the runtime ran on another hart since */
+    let here = ctx.live_on == hartid;
+    let fs = UnitState::from_bits(ctx.mstatus.field(MSTATUS_FS));
+    match on_entry(fs, here && scratch.fp_owner == id) {
+        OnEntry::Keep => {}
+        OnEntry::Load => {
+            unsafe { load_fp(&ctx.float) };
+            scratch.fp_owner = id;
+        }
+        OnEntry::Disown => scratch.fp_owner = NO_OWNER,
+    }
+    if has_vector() {
+        let vs = UnitState::from_bits(ctx.mstatus.field(MSTATUS_VS));
+        match on_entry(vs, here && scratch.vector_owner == id) {
+            OnEntry::Keep => {}
+            OnEntry::Load => {
+                unsafe { load_vector(&ctx.vector) };
+                scratch.vector_owner = id;
+            }
+            OnEntry::Disown => scratch.vector_owner = NO_OWNER,
+        }
+    }
+    ctx.live_on = hartid;
+}
+
+/* after a trap, save only what the runtime changed; Clean and Initial state is already in
+ * its context, Off state does not exist */
+pub fn switch_out(ctx: &mut Context) {
+    let fs = UnitState::from_bits(ctx.mstatus.field(MSTATUS_FS));
+    if let Some(state) = save_on_exit(fs) {
+        unsafe { save_fp(&mut ctx.float) };
+        ctx.mstatus.set_field(MSTATUS_FS, state.bits());
+    }
+    if has_vector() {
+        let vs = UnitState::from_bits(ctx.mstatus.field(MSTATUS_VS));
+        if let Some(state) = save_on_exit(vs) {
+            unsafe { save_vector(&mut ctx.vector) };
+            ctx.mstatus.set_field(MSTATUS_VS, state.bits());
+        }
+    }
+}
+
+/* registers were clobbered behind the owner's back, reload on next entry; callers run right
+ * after the owner's trap, so `switch_out` has saved whatever it changed */
+pub fn invalidate_owner() {
+    let mut scratch = get_hart_scratch(mhartid::read()).lock();
+    scratch.fp_owner = NO_OWNER;
+    scratch.vector_owner = NO_OWNER;
+}
+
+unsafe fn enable_live(field: Range<usize>) {
+    let mut bits: usize = 0;
+    bits.set_bits(field, 1);
+    asm!("csrs mstatus, {}", in(reg) bits);
+}
+
+unsafe fn save_fp(state: &mut FpState) {
+    asm!(
+        "
+        fsd     f0,     0*8({0})
+        fsd     f1,     1*8({0})
+        fsd     f2,     2*8({0})
+        fsd     f3,     3*8({0})
+        fsd     f4,     4*8({0})
+        fsd     f5,     5*8({0})
+        fsd     f6,     6*8({0})
+        fsd     f7,     7*8({0})
+        fsd     f8,     8*8({0})
+        fsd     f9,     9*8({0})
+        fsd     f10,    10*8({0})
+        fsd     f11,    11*8({0})
+        fsd     f12,    12*8({0})
+        fsd     f13,    13*8({0})
+        fsd     f14,    14*8({0})
+        fsd     f15,    15*8({0})
+        fsd     f16,    16*8({0})
+        fsd     f17,    17*8({0})
+        fsd     f18,    18*8({0})
+        fsd     f19,    19*8({0})
+        fsd     f20,    20*8({0})
+        fsd     f21,    21*8({0})
+        fsd     f22,    22*8({0})
+        fsd     f23,    23*8({0})
+        fsd     f24,    24*8({0})
+        fsd     f25,    25*8({0})
+        fsd     f26,    26*8({0})
+        fsd     f27,    27*8({0})
+        fsd     f28,    28*8({0})
+        fsd     f29,    29*8({0})
+        fsd     f30,    30*8({0})
+        fsd     f31,    31*8({0})
+        ",
+        in(reg) state.f.as_mut_ptr(),
+    );
+    asm!("csrr {}, fcsr", out(reg) state.fcsr);
+}
+
+unsafe fn load_fp(state: &FpState) {
+    enable_live(MSTATUS_FS);
+    asm!(
+        "
+        fld     f0,     0*8({0})
+        fld     f1,     1*8({0})
+        fld     f2,     2*8({0})
+        fld     f3,     3*8({0})
+        fld     f4,     4*8({0})
+        fld     f5,     5*8({0})
+        fld     f6,     6*8({0})
+        fld     f7,     7*8({0})
+        fld     f8,     8*8({0})
+        fld     f9,     9*8({0})
+        fld     f10,    10*8({0})
+        fld     f11,    11*8({0})
+        fld     f12,    12*8({0})
+        fld     f13,    13*8({0})
+        fld     f14,    14*8({0})
+        fld     f15,    15*8({0})
+        fld     f16,    16*8({0})
+        fld     f17,    17*8({0})
+        fld     f18,    18*8({0})
+        fld     f19,    19*8({0})
+        fld     f20,    20*8({0})
+        fld     f21,    21*8({0})
+        fld     f22,    22*8({0})
+        fld     f23,    23*8({0})
+        fld     f24,    24*8({0})
+        fld     f25,    25*8({0})
+        fld     f26,    26*8({0})
+        fld     f27,    27*8({0})
+        fld     f28,    28*8({0})
+        fld     f29,    29*8({0})
+        fld     f30,    30*8({0})
+        fld     f31,    31*8({0})
+        ",
+        in(reg) state.f.as_ptr(),
+    );
+    asm!("csrw fcsr, {}", in(reg) state.fcsr);
+}
+
+/* vse.v in 0.7.1 and vse8.v in 1.0 differ only in the width field */
+#[cfg(feature = "sunxi")]
+macro_rules! save_vector_regs {
+    () => {
+        "
+        .word 0x02057027
+        add     a0, a0, t0
+        .word 0x02057427
+        add     a0, a0, t0
+        .word 0x02057827
+        add     a0, a0, t0
+        .word 0x02057c27
+        "
+    };
+}
+
+#[cfg(not(feature = "sunxi"))]
+macro_rules! save_vector_regs {
+    () => {
+        "
+        .word 0x02050027
+        add     a0, a0, t0
+        .word 0x02050427
+        add     a0, a0, t0
+        .word 0x02050827
+        add     a0, a0, t0
+        .word 0x02050c27
+        "
+    };
+}
+
+#[cfg(feature = "sunxi")]
+macro_rules! load_vector_regs {
+    () => {
+        "
+        .word 0x02057007
+        add     a0, a0, t0
+        .word 0x02057407
+        add     a0, a0, t0
+        .word 0x02057807
+        add     a0, a0, t0
+        .word 0x02057c07
+        "
+    };
+}
+
+#[cfg(not(feature = "sunxi"))]
+macro_rules! load_vector_regs {
+    () => {
+        "
+        .word 0x02050007
+        add     a0, a0, t0
+        .word 0x02050407
+        add     a0, a0, t0
+        .word 0x02050807
+        add     a0, a0, t0
+        .word 0x02050c07
+        "
+    };
+}
+
+unsafe fn save_vector(state: &mut VectorState) {
+    let regs = state
+        .regs
+        .get_or_insert_with(|| vec![0u8; 32 * vlenb()].into_boxed_slice());
+    asm!("csrr {}, 0xc20", out(reg) state.vl);
+    asm!("csrr {}, 0xc21", out(reg) state.vtype);
+    asm!("csrr {}, 0x008", out(reg) state.vstart);
+    asm!(
+        /* vsetvli t0, zero, e8, m8 */
+        ".word 0x003072d7",
+        save_vector_regs!(),
+        inout("a0") regs.as_mut_ptr() => _,
+        out("t0") _,
+    );
+}
+
+unsafe fn load_vector(state: &VectorState) {
+    enable_live(MSTATUS_VS);
+    let regs = match state.regs.as_ref() {
+        Some(regs) => regs,
+        /* never saved: the registers only need to lose the last owner's data */
+        None => {
+            clear_vector();
+            return;
+        }
+    };
+    asm!(
+        ".word 0x003072d7",
+        load_vector_regs!(),
+        /* vsetvl zero, t1, t2 */
+        ".word 0x80737057",
+        inout("a0") regs.as_ptr() => _,
+        in("t1") state.vl,
+        in("t2") state.vtype,
+        out("t0") _,
+    );
+    asm!("csrw 0x008, {}", in(reg) state.vstart);
+}
+
+pub unsafe fn clear_fp() {
+    enable_live(MSTATUS_FS);
+    asm!(
+        "
+        fmv.d.x f0, zero
+        fmv.d.x f1, zero
+        fmv.d.x f2, zero
+        fmv.d.x f3, zero
+        fmv.d.x f4, zero
+        fmv.d.x f5, zero
+        fmv.d.x f6, zero
+        fmv.d.x f7, zero
+        fmv.d.x f8, zero
+        fmv.d.x f9, zero
+        fmv.d.x f10, zero
+        fmv.d.x f11, zero
+        fmv.d.x f12, zero
+        fmv.d.x f13, zero
+        fmv.d.x f14, zero
+        fmv.d.x f15, zero
+        fmv.d.x f16, zero
+        fmv.d.x f17, zero
+        fmv.d.x f18, zero
+        fmv.d.x f19, zero
+        fmv.d.x f20, zero
+        fmv.d.x f21, zero
+        fmv.d.x f22, zero
+        fmv.d.x f23, zero
+        fmv.d.x f24, zero
+        fmv.d.x f25, zero
+        fmv.d.x f26, zero
+        fmv.d.x f27, zero
+        fmv.d.x f28, zero
+        fmv.d.x f29, zero
+        fmv.d.x f30, zero
+        fmv.d.x f31, zero
+        csrw    fcsr, zero
+        "
+    );
+}
+
+/* these encodings match both 0.7.1 and 1.0 */
+pub unsafe fn clear_vector() {
+    enable_live(MSTATUS_VS);
+    asm!(
+        "
+        /* vsetvli t0, zero, e8, m8 */
+        .word 0x003072d7
+        /* vmv.v.i v0, 0; v8; v16; v24 */
+        .word 0x5e003057
+        .word 0x5e003457
+        .word 0x5e003857
+        .word 0x5e003c57
+        ",
+        out("t0") _,
+    );
+}
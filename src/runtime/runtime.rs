@@ -1,4 +1,5 @@
 use core::ops::{Generator, GeneratorState};
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 use alloc::boxed::Box;
 use riscv::register::mtvec::{self, Mtvec};
@@ -7,14 +8,19 @@ use crate::println;
 
 use super::super::memory::memory_layout::MemoryLayout;
 use super::context::{from_machine, from_user_or_supervisor, Context};
+use super::extension::{switch_in, switch_out, NO_OWNER};
+
+static NEXT_RUNTIME_ID: AtomicUsize = AtomicUsize::new(NO_OWNER + 1);
 
 pub struct Runtime<Y> {
+    /* tags whose FP/vector state is live on a hart */
+    id: usize,
     /* registers */
     context: Context,
     /* pmp layout */
     layout: Option<MemoryLayout>,
     /* exception handler */
-    exception_handler: Box<dyn FnMut(*mut Context) -> Option<Y>>,
+    exception_handler: Box<dyn FnMut(*mut Context) -> Option<Y> + Send>,
     global_mtvec: Mtvec,
 }
 
@@ -22,15 +28,33 @@ impl<Y> Runtime<Y> {
     pub fn new(
         context: Context,
         layout: Option<MemoryLayout>,
-        exception_handler: Box<dyn FnMut(*mut Context) -> Option<Y>>,
+        exception_handler: Box<dyn FnMut(*mut Context) -> Option<Y> + Send>,
     ) -> Self {
         Runtime {
+            id: NEXT_RUNTIME_ID.fetch_add(1, Ordering::Relaxed),
             context,
             layout,
             exception_handler,
             global_mtvec: riscv::register::mtvec::read(),
         }
     }
+
+    pub fn context_mut(&mut self) -> &mut Context {
+        &mut self.context
+    }
+
+    /* S-mode guests need their own satp, stvec... on top of the GPRs */
+    pub fn switch_supervisor_csrs(&mut self, enable: bool) {
+        self.context.switch_supervisor = enable as usize;
+    }
+
+    pub fn layout(&self) -> Option<&MemoryLayout> {
+        self.layout.as_ref()
+    }
+
+    pub fn layout_mut(&mut self) -> Option<&mut MemoryLayout> {
+        self.layout.as_mut()
+    }
 }
 
 impl<Y> Generator for Runtime<Y> {
@@ -44,11 +68,16 @@ impl<Y> Generator for Runtime<Y> {
         arg: (),
     ) -> core::ops::GeneratorState<Self::Yield, Self::Return> {
         let context_pointer = &mut self.context as *mut Context;
+        if let Some(layout) = self.layout.as_ref() {
+            layout.enforce();
+        }
         self.global_mtvec = riscv::register::mtvec::read();
         let addr = from_user_or_supervisor as usize;
         unsafe { mtvec::write(addr, mtvec::TrapMode::Direct) }
         loop {
+            switch_in(self.id, &mut self.context);
             unsafe { from_machine(context_pointer) };
+            switch_out(&mut self.context);
             if let Some(yield_value) = (self.exception_handler)(context_pointer) {
                 unsafe {
                     mtvec::write(
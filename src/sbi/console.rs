@@ -90,3 +90,13 @@ macro_rules! println {
         $crate::sbi::console::_print(core::format_args!(core::concat!($fmt, "\r\n") $(, $($arg)+)?))
     }
 }
+
+/* chatter for debug builds only */
+#[macro_export(local_inner_macro)]
+macro_rules! debug {
+    ($fmt: literal $(, $($arg: tt)+)?) => {
+        if cfg!(debug_assertions) {
+            $crate::println!(core::concat!("[DEBUG]: ", $fmt) $(, $($arg)+)?)
+        }
+    }
+}
@@ -16,9 +16,9 @@ impl HartMask {
         if self.base == usize::MAX {
             return true;
         }
-        if hartid < self.base {
-            return false;
+        match hartid.checked_sub(self.base) {
+            Some(bit) if bit < usize::BITS as usize => self.mask.get_bit(bit),
+            _ => false,
         }
-        self.mask.get_bit(hartid - self.base)
     }
 }
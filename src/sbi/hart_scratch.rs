@@ -1,19 +1,28 @@
 use core::intrinsics::atomic_xchg;
 use core::arch::asm;
 
-use crate::util::fdt::detect_hart;
+use crate::runtime::extension::NO_OWNER;
+use crate::sbi::hart_table::{hart_count, hart_index};
 use alloc::vec::Vec;
 use bit_field::BitField;
 use spin::{Mutex, RwLock};
 
 pub struct HartScratch {
     pub ipi_scratch: IpiScratch,
+    /* deadline last requested by S-mode through `set_timer`, if not fired yet */
+    pub stime_deadline: Option<u64>,
+    /* runtime whose FP/vector state sits in the registers */
+    pub fp_owner: usize,
+    pub vector_owner: usize,
 }
 
 impl HartScratch {
     pub fn new() -> Self {
         return Self {
             ipi_scratch: IpiScratch::new(),
+            stime_deadline: None,
+            fp_owner: NO_OWNER,
+            vector_owner: NO_OWNER,
         };
     }
 }
@@ -22,7 +31,7 @@ static mut HART_SCRATCH: Vec<Mutex<HartScratch>> = Vec::new();
 
 pub fn init_hart_scratch() {
     /* need init fdt before this */
-    let hart_cnt = detect_hart();
+    let hart_cnt = hart_count();
     unsafe {
         for _ in 0..hart_cnt {
             HART_SCRATCH.push(Mutex::new(HartScratch::new()))
@@ -30,8 +39,10 @@ pub fn init_hart_scratch() {
     }
 }
 
+/* hart IDs may be sparse, the scratch of each hart sits at its hart table index */
 pub fn get_hart_scratch(hartid: usize) -> &'static Mutex<HartScratch> {
-    unsafe { &HART_SCRATCH[hartid] }
+    let index = hart_index(hartid).expect("no scratch for a hart the fdt does not list");
+    unsafe { &HART_SCRATCH[index] }
 }
 
 pub struct IpiScratch {
@@ -0,0 +1,92 @@
+use alloc::{string::String, vec::Vec};
+use riscv::register::mhartid;
+use spin::Once;
+
+use crate::{
+    fdt::{prop::FdtProp, Fdt},
+    println,
+};
+
+/* one /cpus/cpu@* node; the strings are copied, the tree may be rewritten later */
+pub struct HartInfo {
+    pub hartid: usize,
+    pub status: String,
+    pub isa: Option<String>,
+    /* riscv,isa-extensions, the newer spelling of the same */
+    pub isa_extensions: Vec<String>,
+    pub mmu_type: Option<String>,
+    /* phandle of its riscv,cpu-intc child */
+    pub intc_phandle: Option<u32>,
+}
+
+impl HartInfo {
+    /* "okay" is also what a node without status gets */
+    pub fn is_enabled(&self) -> bool {
+        self.status == "okay" || self.status == "ok"
+    }
+}
+
+/* sorted by hart ID, per-hart state is indexed by position in here */
+static HART_TABLE: Once<Vec<HartInfo>> = Once::new();
+
+pub fn init_hart_table(fdt: &Fdt) {
+    HART_TABLE.call_once(|| {
+        let string = |prop: Option<FdtProp>| {
+            prop.and_then(|prop| prop.as_str()).map(String::from)
+        };
+        let mut harts = Vec::new();
+        for cpu in fdt.cpus() {
+            let hartid = match cpu.reg().and_then(|mut reg| reg.next()) {
+                Some(reg) => reg.address as usize,
+                None => {
+                    println!("[ERROR]: {} has no hart ID, ignored", cpu.path());
+                    continue;
+                }
+            };
+            let intc = cpu
+                .children()
+                .find(|child| child.is_compatible(&["riscv,cpu-intc"]));
+            harts.push(HartInfo {
+                hartid,
+                status: string(cpu.property("status")).unwrap_or_else(|| String::from("okay")),
+                isa: string(cpu.property("riscv,isa")),
+                isa_extensions: cpu
+                    .property("riscv,isa-extensions")
+                    .map(|prop| prop.as_strings().map(String::from).collect())
+                    .unwrap_or_default(),
+                mmu_type: string(cpu.property("mmu-type")),
+                intc_phandle: intc.and_then(|intc| intc.phandle()),
+            });
+        }
+        harts.sort_by_key(|hart| hart.hartid);
+        harts.dedup_by_key(|hart| hart.hartid);
+        harts
+    });
+}
+
+pub fn harts() -> &'static [HartInfo] {
+    HART_TABLE.get().expect("hart table used before the fdt")
+}
+
+pub fn hart_count() -> usize {
+    harts().len()
+}
+
+/* one past the highest hart ID, the bound for hart indexed MMIO like the clint */
+pub fn hartid_limit() -> usize {
+    harts().last().map_or(0, |hart| hart.hartid + 1)
+}
+
+/* dense index of a sparse hart ID */
+pub fn hart_index(hartid: usize) -> Option<usize> {
+    harts().binary_search_by_key(&hartid, |hart| hart.hartid).ok()
+}
+
+pub fn hart_id(index: usize) -> usize {
+    harts()[index].hartid
+}
+
+/* index of the calling hart, which has to be in the tree */
+pub fn current_hart() -> usize {
+    hart_index(mhartid::read()).expect("running on a hart the fdt does not list")
+}
@@ -1,6 +1,7 @@
 use super::{
     hart_mask::HartMask,
     hart_scratch::get_hart_scratch,
+    hart_table::harts,
     ipi_event::{create_ipi_event, get_ipi_evnet, IpiEvent, IpiEventOps},
     rfence,
     sbiret::SbiRet,
@@ -46,11 +47,13 @@ pub(crate) fn send_ipi_smode(hart_mask: HartMask) {
     send_ipi_many(hart_mask, smode_event_id);
 }
 
+/* harts the tree marks disabled never boot, nothing would take the event off their scratch */
 pub(crate) fn send_ipi_many(hart_mask: HartMask, event_id: usize) -> SbiRet {
     if let Some(ipi) = IPI.lock().as_ref() {
-        for i in 0..ipi.max_hartid() {
-            if hart_mask.has(i) {
-                send_ipi(ipi, i, event_id);
+        let targets = harts().iter().filter(|hart| hart.is_enabled());
+        for hartid in targets.map(|hart| hart.hartid) {
+            if hartid < ipi.max_hartid() && hart_mask.has(hartid) {
+                send_ipi(ipi, hartid, event_id);
             }
         }
         SbiRet::ok(0)
@@ -59,6 +62,17 @@ pub(crate) fn send_ipi_many(hart_mask: HartMask, event_id: usize) -> SbiRet {
     }
 }
 
+/* one hart picked by hart ID, false when it cannot be reached */
+pub(crate) fn send_ipi_one(hartid: usize, event_id: usize) -> bool {
+    match IPI.lock().as_ref() {
+        Some(ipi) if hartid < ipi.max_hartid() => {
+            send_ipi(ipi, hartid, event_id);
+            true
+        }
+        _ => false,
+    }
+}
+
 pub(crate) fn clear_ipi(ipi: &Box<dyn Ipi>, hartid: usize) {
     ipi.clear_soft_irq(hartid);
 }
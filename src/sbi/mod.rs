@@ -10,6 +10,7 @@ pub mod timer;
 pub mod fence_info;
 pub mod hart_mask;
 pub mod hart_scratch;
+pub mod hart_table;
 
 pub use console::*;
 pub const SBI_SPEC_MAJOR: usize = 0;
@@ -24,6 +25,8 @@ pub const EXT_RFENCE: usize = 0x5246_4E43;
 pub const EXT_HSM: usize = 0x48_534D;
 pub const EXT_SRST: usize = 0x5352_5354;
 pub const EXT_PMU: usize = 0x50_4D55;
+/* vendor extension space, "\tCOF" */
+pub const EXT_COFFER: usize = 0x0943_4F46;
 
 pub const LEGACY_TIMER: usize = 0x0;
 pub const LEGACY_PUTCHAR: usize = 0x1;
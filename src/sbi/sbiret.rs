@@ -27,6 +27,13 @@ impl SbiRet {
         }
     }
 
+    pub fn failed() -> SbiRet {
+        SbiRet {
+            error: sbi_error::FAILED,
+            value: 0,
+        }
+    }
+
     pub fn not_supported() -> SbiRet {
         SbiRet {
             error: sbi_error::NOT_SUPPORTED,
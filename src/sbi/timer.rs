@@ -1,9 +1,14 @@
 use crate::{println, util::status::print_machine};
 
-use super::{hart_mask, sbiret::SbiRet};
-use riscv::register::{mie, mip};
+use super::{hart_mask, hart_scratch::get_hart_scratch, sbiret::SbiRet};
+use riscv::register::{mhartid, mie, mip, time};
 pub trait Timer: Send {
     fn set_timer(&self, stime_value: u64);
+
+    /* the counter deadlines are compared against */
+    fn mtime(&self) -> u64 {
+        time::read64()
+    }
 }
 
 use alloc::boxed::Box;
@@ -23,6 +28,7 @@ where
 pub(crate) fn set_timer(stime_value: u64) -> SbiRet {
     if let Some(timer) = TIMER.lock().as_mut() {
         timer.set_timer(stime_value);
+        get_hart_scratch(mhartid::read()).lock().stime_deadline = Some(stime_value);
         unsafe {
             mip::clear_stimer();
             mie::set_mtimer();
@@ -34,12 +40,50 @@ pub(crate) fn set_timer(stime_value: u64) -> SbiRet {
 }
 
 pub(crate) fn process_timer() {
+    get_hart_scratch(mhartid::read()).lock().stime_deadline = None;
     unsafe {
         mie::clear_mtimer();
         mip::set_stimer();
     }
 }
 
+/* borrow the machine timer for coffer itself, an earlier S-mode deadline still fires */
+pub(crate) fn set_machine_timer(mtime_value: u64) -> bool {
+    let deadline = get_hart_scratch(mhartid::read()).lock().stime_deadline;
+    if let Some(timer) = TIMER.lock().as_ref() {
+        timer.set_timer(deadline.map_or(mtime_value, |d| d.min(mtime_value)));
+        unsafe { mie::set_mtimer() };
+        true
+    } else {
+        false
+    }
+}
+
+/* hand the machine timer back to S-mode, an expired deadline fires right away */
+pub(crate) fn restore_supervisor_timer() {
+    let deadline = get_hart_scratch(mhartid::read()).lock().stime_deadline;
+    if let Some(timer) = TIMER.lock().as_ref() {
+        match deadline {
+            Some(stime_value) => {
+                timer.set_timer(stime_value);
+                unsafe { mie::set_mtimer() };
+            }
+            None => {
+                timer.set_timer(u64::MAX);
+                unsafe { mie::clear_mtimer() };
+            }
+        }
+    }
+}
+
+/* mtime as the bound timer reads it, the time CSR until one is bound */
+pub(crate) fn read_mtime() -> u64 {
+    match TIMER.lock().as_ref() {
+        Some(timer) => timer.mtime(),
+        None => time::read64(),
+    }
+}
+
 pub(crate) fn probe_timer() -> SbiRet {
     if let Some(_) = TIMER.lock().as_ref() {
         SbiRet::ok(1)
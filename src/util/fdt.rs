@@ -1,6 +1,5 @@
-use alloc::boxed::Box;
-use fdt::{node::FdtNode, Fdt, FdtError};
-use if_chain::if_chain;
+use alloc::{format, vec::Vec};
+use core::ops::Range;
 use spin::Mutex;
 
 #[cfg(target_arch = "riscv64")]
@@ -9,100 +8,364 @@ pub const XLEN: usize = 64;
 pub const XLEN: usize = 32;
 
 use crate::{
-    hal::{Clint, Clint32, Ns16550a, SifiveUart, SunxiUart},
-    println,
-    sbi::{init_console_embedded_serial, ipi::init_ipi, timer::init_timer},
+    fdt::{
+        builder::{BuilderNode, FdtBuilder},
+        overlay::apply_overlay,
+        Fdt,
+    },
+    hal::{
+        driver::{probe_all, Provider},
+        Clint32,
+    },
+    memory::{
+        memory_layout::{image_range, napot_blocks, Region, HOST_LAYOUT},
+        pmp::PmpFlags,
+    },
+    debug, println,
+    sbi::{
+        hart_table::{hartid_limit, init_hart_table},
+        ipi::init_ipi,
+        timer::init_timer,
+    },
 };
 
 lazy_static::lazy_static! {
-    pub static ref FDT: Mutex<Option<Box<Fdt<'static>>>> = Mutex::new(None);
+    pub static ref FDT: Mutex<Option<Fdt>> = Mutex::new(None);
 }
 
-pub fn init_fdt(fdt_addr: usize) -> Result<(), FdtError> {
-    unsafe {
-        let fdt = Fdt::from_ptr(fdt_addr as *const u8)?;
-        *FDT.lock() = Some(Box::new(fdt));
+pub fn init_fdt(fdt_addr: usize) -> Result<(), &'static str> {
+    init_fdt_with_overlays(fdt_addr, &[])
+}
+
+/* include_bytes! gives no alignment, blobs built into coffer are wrapped in this */
+#[repr(C, align(8))]
+pub struct Aligned<T>(pub T);
+
+/* .dtbo files listed in COFFER_OVERLAYS at build time */
+static EMBEDDED_OVERLAYS: &[&[u8]] = &include!(concat!(env!("OUT_DIR"), "/overlays.rs"));
+
+/* the overlaid tree, kept so patch_dtb starts from it rather than from a reparse */
+lazy_static::lazy_static! {
+    static ref OVERLAID: Mutex<Option<FdtBuilder>> = Mutex::new(None);
+}
+
+/* the embedded overlays, then the ones at `overlays`, applied over the tree at `fdt_addr`;
+ * probing and the OS both see the result */
+pub fn init_fdt_with_overlays(fdt_addr: usize, overlays: &[usize]) -> Result<(), &'static str> {
+    let base = unsafe { Fdt::from_ptr(fdt_addr as *const u8)? };
+    if EMBEDDED_OVERLAYS.is_empty() && overlays.is_empty() {
+        init_hart_table(&base);
+        *FDT.lock() = Some(base);
+        return Ok(());
     }
+    let mut tree = FdtBuilder::from_fdt(&base)?;
+    let memory = memory_ranges(&base);
+    let blobs = EMBEDDED_OVERLAYS
+        .iter()
+        .map(|blob| (blob.as_ptr() as usize, Fdt::from_bytes(blob)))
+        .chain(
+            overlays
+                .iter()
+                .map(|addr| (*addr, unsafe { boot_blob(&memory, *addr) })),
+        );
+    for (addr, overlay) in blobs {
+        let applied = overlay.and_then(|overlay| apply_overlay(&mut tree, &overlay));
+        if let Err(e) = applied {
+            println!("{}: overlay at {:#x} skipped", e, addr);
+        }
+    }
+    /* FDT keeps pointing into this one, patch_dtb must not reuse it */
+    let fdt = unsafe {
+        tree.serialize(&mut PROBE_BUFFER.0)?;
+        Fdt::from_bytes(&PROBE_BUFFER.0)?
+    };
+    init_hart_table(&fdt);
+    *FDT.lock() = Some(fdt);
+    *OVERLAID.lock() = Some(tree);
     Ok(())
 }
 
-pub fn init_sunxi_clint(base_addr: usize) {
-    let cpucnt = detect_hart();
-    let clint = Clint32::new(base_addr, 0x4000, cpucnt);
-    init_ipi(clint);
-    let clint = Clint32::new(base_addr, 0x4000, cpucnt);
-    init_timer(clint);
+/* room for the patched tree, it may grow past the original */
+const DTB_BUFFER_SIZE: usize = 128 * 1024;
+
+#[repr(C, align(8))]
+struct DtbBuffer([u8; DTB_BUFFER_SIZE]);
+
+#[link_section = ".dtb"]
+static mut DTB_BUFFER: DtbBuffer = DtbBuffer([0; DTB_BUFFER_SIZE]);
+
+/* the overlaid tree coffer probes with, private to coffer unlike DTB_BUFFER */
+static mut PROBE_BUFFER: DtbBuffer = DtbBuffer([0; DTB_BUFFER_SIZE]);
+
+/* the ranges of the memory nodes, the only place the boot stage may leave a blob */
+pub fn memory_ranges(fdt: &Fdt) -> Vec<Range<usize>> {
+    fdt.node_iter()
+        .filter(|node| {
+            node.property("device_type").and_then(|prop| prop.as_str()) == Some("memory")
+        })
+        .flat_map(|node| node.reg().into_iter().flatten())
+        .filter_map(|reg| {
+            let start = reg.address as usize;
+            Some(start..start.checked_add(reg.size? as usize)?)
+        })
+        .collect()
 }
 
-pub fn detect_clint() {
-    if_chain! {
-        if let Some(fdt) = FDT.lock().as_ref();
-        if let Some(node) = fdt.find_compatible(&["riscv,clint0"]);
-        if let Some(mut reg_list) = node.reg();
-        if let Some(reg) = reg_list.next();
-        then {
-            let base = reg.starting_address;
-            let cpucnt = fdt.cpus().count();
-            let clint = Clint::new(base as usize, 0x4000, cpucnt);
-            init_ipi(clint);
-            let clint = Clint::new(base as usize, 0x4000, cpucnt);
-            init_timer(clint);
-        } else {
-            panic!("init clint failed");
-        }
+/* a blob the boot stage left at `addr`, its total_size is only believed once the whole of it
+ * is known to sit in `memory` and clear of coffer's image */
+unsafe fn boot_blob(memory: &[Range<usize>], addr: usize) -> Result<Fdt, &'static str> {
+    let image = image_range();
+    if image.contains(&addr) {
+        return Err("[ERROR]: blob is inside coffer");
+    }
+    let mut end = memory
+        .iter()
+        .find(|range| range.contains(&addr))
+        .ok_or("[ERROR]: blob is not in memory")?
+        .end;
+    if addr < image.start {
+        end = end.min(image.start);
     }
+    if addr % 4 != 0 || end - addr < 8 {
+        return Err("[ERROR]: blob has no room for a header");
+    }
+    let head = core::slice::from_raw_parts(addr as *const u8, 8);
+    let total_size = u32::from_be_bytes([head[4], head[5], head[6], head[7]]) as usize;
+    if total_size > end - addr {
+        return Err("[ERROR]: blob runs out of memory");
+    }
+    Fdt::from_bytes(core::slice::from_raw_parts(addr as *const u8, total_size))
+}
+
+/* what coffer changes in the tree S-mode gets */
+fn fixup_dtb(tree: &mut FdtBuilder) {
+    tree.add_node("/firmware/coffer")
+        .set_str("compatible", "coffer,sbi");
+    hide_secure_nodes(tree.root_mut());
+    /* donated pool memory is not reserved here: the host hands it over through an ecall,
+     * long after this tree was passed on, and stops using it itself; memory coffer should
+     * own from boot has to be carved out by the board's firmware configuration instead */
+    reserve_memory(tree, "coffer", image_range());
+}
+
+/* devices kept for coffer and enclaves, marked `coffer,secure` or status = "secure-okay" */
+fn is_secure(secure: bool, status: Option<&[u8]>) -> bool {
+    secure || status == Some(b"secure-okay\0")
 }
 
-pub fn detect_sunxi_uart() {
-    if_chain! {
-            if let Some(fdt) = FDT.lock().as_ref();
-            if let Some(node) = fdt.find_compatible(&["allwinner,sun20i-uart"]);
-            if let Some(mut reg_list) = node.reg();
-            if let Some(reg) = reg_list.next();
-            then {
-                let base = reg.starting_address;
-                let serial = SunxiUart::new(base as usize);
-                init_console_embedded_serial(serial);
+/* S-mode sees secure devices as disabled, phandles pointing at them stay valid */
+fn hide_secure_nodes(node: &mut BuilderNode) {
+    if is_secure(
+        node.property("coffer,secure").is_some(),
+        node.property("status"),
+    ) {
+        node.set_str("status", "disabled");
+    }
+    for child in node.children_mut() {
+        hide_secure_nodes(child);
+    }
+}
+
+/* deny the host every reg range of a secure device, before HOST_LAYOUT is first enforced */
+pub fn protect_secure_devices() {
+    let fdt = FDT.lock();
+    let fdt = match fdt.as_ref() {
+        Some(fdt) => fdt,
+        None => return,
+    };
+    let secure = fdt.node_iter().filter(|node| {
+        is_secure(
+            node.property("coffer,secure").is_some(),
+            node.property("status").map(|prop| prop.value()),
+        )
+    });
+    let mut host = HOST_LAYOUT.lock();
+    for node in secure {
+        for reg in node.reg().into_iter().flatten() {
+            /* pmp works on 8 byte granules at least */
+            let start = reg.address as usize & !7;
+            let end = match reg.size.filter(|size| *size > 0) {
+                Some(size) => (reg.address as usize)
+                    .checked_add(size as usize)
+                    .and_then(|end| end.checked_add(7)),
+                None => {
+                    println!(
+                        "[ERROR]: {} has no size at {:#x}, it stays visible to S-mode",
+                        node.path(),
+                        reg.address
+                    );
+                    continue;
+                }
+            };
+            let end = match end {
+                Some(end) => end & !7,
+                None => {
+                    println!(
+                        "[ERROR]: {} at {:#x} runs past the address space, it stays visible to S-mode",
+                        node.path(),
+                        reg.address
+                    );
+                    continue;
+                }
+            };
+            for block in napot_blocks(start..end) {
+                let deny = Region::napot(block.start, block.end - block.start, PmpFlags::empty())
+                    .and_then(|region| host.add_region(region));
+                if let Err(e) = deny {
+                    println!("{}: {} stays visible to S-mode", e, node.path());
+                }
             }
+        }
+    }
+}
 
+/* `value` in `cells` big-endian cells */
+fn push_cells(out: &mut Vec<u32>, value: u64, cells: u32) {
+    if cells == 2 {
+        out.push((value >> 32) as u32);
     }
+    out.push(value as u32);
 }
 
-pub fn detect_sifive_uart() {
-    if_chain! {
-            if let Some(fdt) = FDT.lock().as_ref();
-            if let Some(node) = fdt.find_compatible(&["sifive,uart0"]);
-            if let Some(mut reg_list) = node.reg();
-            if let Some(reg) = reg_list.next();
-            then {
-                let base = reg.starting_address;
-                let serial = SifiveUart::new(base as usize, 0, 115200);
-                init_console_embedded_serial(serial);
+/* the reg ranges of the existing /reserved-memory children */
+fn reserved_ranges(tree: &FdtBuilder) -> Vec<Range<u64>> {
+    let node = match tree.node("/reserved-memory") {
+        Some(node) => node,
+        None => return Vec::new(),
+    };
+    let address_cells = node.property_u32("#address-cells").unwrap_or(2) as usize;
+    let size_cells = node.property_u32("#size-cells").unwrap_or(1) as usize;
+    let read = |cells: &[u8]| {
+        cells.chunks_exact(4).fold(0u64, |acc, c| {
+            (acc << 32) | u32::from_be_bytes([c[0], c[1], c[2], c[3]]) as u64
+        })
+    };
+    let entry = (address_cells + size_cells) * 4;
+    /* a reg nobody can split into entries, reserves nothing we can tell */
+    if entry == 0 {
+        return Vec::new();
+    }
+    node.children()
+        .filter_map(|child| child.property("reg"))
+        .flat_map(|reg| reg.chunks_exact(entry))
+        .map(|reg| {
+            let address = read(&reg[..address_cells * 4]);
+            address..address.saturating_add(read(&reg[address_cells * 4..]))
+        })
+        .collect()
+}
+
+/* keep S-mode away from `range`: a no-map /reserved-memory child and a memreserve entry */
+fn reserve_memory(tree: &mut FdtBuilder, name: &str, range: Range<usize>) {
+    let (start, end) = (range.start as u64, range.end as u64);
+    let overlaps = |r: &Range<u64>| r.start < end && start < r.end;
+    for &(address, size) in tree.reservations().iter() {
+        let r = match address.checked_add(size) {
+            Some(end) => address..end,
+            None => {
+                println!(
+                    "[WARN]: memreserve {:#x}+{:#x} wraps around, ignored",
+                    address, size
+                );
+                continue;
             }
+        };
+        if overlaps(&r) {
+            println!(
+                "[WARN]: {} {:#x}..{:#x} overlaps memreserve {:#x}..{:#x}",
+                name, start, end, r.start, r.end
+            );
+        }
+    }
+    for r in reserved_ranges(tree) {
+        if overlaps(&r) {
+            println!(
+                "[WARN]: {} {:#x}..{:#x} overlaps reserved-memory {:#x}..{:#x}",
+                name, start, end, r.start, r.end
+            );
+        }
+    }
+    tree.add_reservation(start, end - start);
 
+    let root = tree.root();
+    let address_cells = root.property_u32("#address-cells").unwrap_or(2);
+    let size_cells = root.property_u32("#size-cells").unwrap_or(1);
+    /* an existing node keeps its cells, its other children are encoded with them */
+    let created = tree.node("/reserved-memory").is_none();
+    let reserved = tree.add_node("/reserved-memory");
+    if created {
+        reserved.set_u32("#address-cells", address_cells);
+        reserved.set_u32("#size-cells", size_cells);
+    }
+    if reserved.property("ranges").is_none() {
+        reserved.set_empty("ranges");
     }
+    let address_cells = reserved.property_u32("#address-cells").unwrap_or(2);
+    let size_cells = reserved.property_u32("#size-cells").unwrap_or(1);
+    let mut reg = Vec::new();
+    push_cells(&mut reg, start, address_cells);
+    push_cells(&mut reg, end - start, size_cells);
+    let child = reserved.add_child(format!("{}@{:x}", name, start));
+    child.set_cells("reg", &reg);
+    child.set_empty("no-map");
 }
 
-pub fn detect_ns16550a() {
-    if_chain! {
-            if let Some(fdt) = FDT.lock().as_ref();
-            if let Some(node) = fdt.find_compatible(&["ns16550a"]);
-            if let Some(mut reg_list) = node.reg();
-            if let Some(reg) = reg_list.next();
-            if let Some(clock) = node.property("clock-frequency");
-            if let Some(clk) = clock.as_usize();
-            then {
-                let base = reg.starting_address;
-                let serial = Ns16550a::new(base as usize, 0, clk as u64, 115200);
-                init_console_embedded_serial(serial)
+/* the overlaid tree, else the incoming one, or the one coffer probed with when there is
+ * none, patched into DTB_BUFFER; its address goes to the kernel in a1 */
+pub fn patch_dtb(dtb: usize) -> usize {
+    let overlaid = OVERLAID.lock().take();
+    let mut tree = match overlaid {
+        Some(tree) => tree,
+        None => {
+            let source = match unsafe { Fdt::from_ptr(dtb as *const u8) } {
+                Ok(fdt) => fdt,
+                Err(_) => match FDT.lock().as_ref() {
+                    Some(fdt) => unsafe { Fdt::from_ptr(fdt.as_bytes().as_ptr()).unwrap() },
+                    None => return dtb,
+                },
+            };
+            match FdtBuilder::from_fdt(&source) {
+                Ok(tree) => tree,
+                Err(e) => {
+                    println!("{}, passing the dtb unchanged", e);
+                    return dtb;
+                }
             }
+        }
+    };
+    fixup_dtb(&mut tree);
+    match tree.serialize(unsafe { &mut DTB_BUFFER.0 }) {
+        Ok(_) => unsafe { DTB_BUFFER.0.as_ptr() as usize },
+        Err(e) => {
+            println!("{}, passing the dtb unchanged", e);
+            dtb
+        }
     }
 }
 
-pub fn detect_hart() -> usize {
-    if let Some(fdt) = FDT.lock().as_ref() {
-        fdt.cpus().count()
-    } else {
-        panic!("hart detect without fdt");
+pub fn init_sunxi_clint(base_addr: usize) {
+    let cpucnt = hartid_limit();
+    let clint = Clint32::new(base_addr, 0x4000, cpucnt);
+    init_ipi(clint);
+    let clint = Clint32::new(base_addr, 0x4000, cpucnt);
+    init_timer(clint);
+}
+
+/* bind console, ipi, timer, reset and fence providers in one pass over the tree */
+pub fn probe_drivers() {
+    let bound = match FDT.lock().as_ref() {
+        Some(fdt) => probe_all(fdt),
+        None => panic!("probe drivers without fdt"),
+    };
+    for provider in [
+        Provider::Console,
+        Provider::Ipi,
+        Provider::Timer,
+        Provider::Reset,
+        Provider::Fence,
+    ] {
+        if !bound.contains(&provider) {
+            debug!("no {:?} provider in the device tree", provider);
+        }
     }
 }
@@ -1,3 +1,4 @@
+use crate::features::current_features;
 use crate::memory::pmp::PmpFlags;
 use crate::memory::pmp::{pmpaddr_read, pmpcfg_read};
 use crate::{print, println};
@@ -40,9 +41,22 @@ pub fn print_misa() {
         };
         println!("ISA: riscv{}, ", xlen);
         print!("Supported Extensions:");
-        for ext in 'A'..='Z' {
-            if misa.has_extension(ext) {
-                print!("{}", ext);
+        /* the parsed set once init_features ran, misa alone before */
+        match current_features() {
+            Some(features) => {
+                for ext in features.letters() {
+                    print!("{}", ext.to_ascii_uppercase());
+                }
+                for ext in features.multi_letter() {
+                    print!("_{}", ext);
+                }
+            }
+            None => {
+                for ext in 'A'..='Z' {
+                    if misa.has_extension(ext) {
+                        print!("{}", ext);
+                    }
+                }
             }
         }
         println!("");
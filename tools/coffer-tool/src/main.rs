@@ -0,0 +1,195 @@
+use std::{env, fs, io::Read, process};
+
+use coffer_common::{
+    bundle::{BundleHeader, Manifest, HEADER_SIZE},
+    image::{Image, PAGE_SIZE},
+    measure::Measurement,
+    report::Report,
+};
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature, Signer, Verifier};
+use goblin::elf::Elf;
+
+const BUNDLE_SIGNATURE_DOMAIN: &[u8] = b"coffer-bundle-v1";
+
+const USAGE: &str = "usage:
+    coffer-tool pack <elf> <manifest> <bundle>
+    coffer-tool measure <bundle>
+    coffer-tool keygen <seed>
+    coffer-tool sign <bundle> <seed> <signature>
+    coffer-tool verify-report <report> <device-public-key-hex> [bundle]";
+
+type Result<T> = std::result::Result<T, String>;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(text: &str) -> Result<Vec<u8>> {
+    let text = text.trim();
+    if text.len() % 2 != 0 {
+        return Err(format!("odd length hex string {}", text));
+    }
+    (0..text.len() / 2)
+        .map(|i| u8::from_str_radix(&text[2 * i..2 * i + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
+
+fn read(path: &str) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn write(path: &str, bytes: &[u8]) -> Result<()> {
+    fs::write(path, bytes).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn parse_number(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(&hex.replace('_', ""), 16).ok(),
+        None => text.replace('_', "").parse().ok(),
+    }
+}
+
+/* numbers or symbol names, resolved against the ELF symbol table */
+fn resolve(elf: &Elf, value: &str) -> Result<u64> {
+    if let Some(number) = parse_number(value) {
+        return Ok(number);
+    }
+    elf.syms
+        .iter()
+        .find(|sym| elf.strtab.get_at(sym.st_name) == Some(value))
+        .map(|sym| sym.st_value)
+        .ok_or_else(|| format!("no symbol {} in the elf", value))
+}
+
+/* `key = value` lines, `#` starts a comment; entry defaults to the ELF entry, stacks to a page */
+fn parse_manifest(text: &str, elf: &Elf) -> Result<Manifest> {
+    let mut manifest = Manifest {
+        memory_size: 0,
+        stack_size: PAGE_SIZE as u64,
+        entry: elf.entry,
+        threads: 1,
+        trap_handler: 0,
+        trap_delegated: 0,
+        scrub_exempt: 0,
+    };
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut parts = line.splitn(2, '=');
+        let key = parts.next().unwrap().trim();
+        let value = parts
+            .next()
+            .ok_or_else(|| format!("expected `key = value`: {}", line))?
+            .trim();
+        let field = match key {
+            "memory_size" => &mut manifest.memory_size,
+            "stack_size" => &mut manifest.stack_size,
+            "entry" => &mut manifest.entry,
+            "threads" => &mut manifest.threads,
+            "trap_handler" => &mut manifest.trap_handler,
+            "trap_delegated" => &mut manifest.trap_delegated,
+            "scrub_exempt" => &mut manifest.scrub_exempt,
+            _ => return Err(format!("unknown manifest key {}", key)),
+        };
+        *field = resolve(elf, value)?;
+    }
+    Ok(manifest)
+}
+
+fn pack(elf_path: &str, manifest_path: &str, out: &str) -> Result<()> {
+    let elf_bytes = read(elf_path)?;
+    let elf = Elf::parse(&elf_bytes).map_err(|e| format!("{}: {}", elf_path, e))?;
+    let text =
+        fs::read_to_string(manifest_path).map_err(|e| format!("{}: {}", manifest_path, e))?;
+    let header = BundleHeader {
+        manifest: parse_manifest(&text, &elf)?,
+        elf_offset: HEADER_SIZE as u64,
+        elf_size: elf_bytes.len() as u64,
+    };
+    let mut bundle = header.to_bytes().to_vec();
+    bundle.extend_from_slice(&elf_bytes);
+    /* reject what coffer would reject */
+    let measurement = measure_bundle(&bundle)?;
+    write(out, &bundle)?;
+    println!("{}", hex(&measurement));
+    Ok(())
+}
+
+/* load the image exactly like coffer does and hash the result */
+fn measure_bundle(bundle: &[u8]) -> Result<Measurement> {
+    let image = Image::parse(bundle)?;
+    let mut region = vec![0u8; image.manifest.memory_size as usize];
+    image.load_into(&mut region);
+    Ok(image.measure(&region))
+}
+
+fn keypair(seed_path: &str) -> Result<Keypair> {
+    let seed = read(seed_path)?;
+    let secret = SecretKey::from_bytes(&seed).map_err(|e| format!("{}: {}", seed_path, e))?;
+    let public = PublicKey::from(&secret);
+    Ok(Keypair { secret, public })
+}
+
+fn keygen(out: &str) -> Result<()> {
+    let mut seed = [0u8; 32];
+    fs::File::open("/dev/urandom")
+        .and_then(|mut f| f.read_exact(&mut seed))
+        .map_err(|e| format!("/dev/urandom: {}", e))?;
+    write(out, &seed)?;
+    println!("{}", hex(&keypair(out)?.public.to_bytes()));
+    Ok(())
+}
+
+fn sign(bundle_path: &str, seed_path: &str, out: &str) -> Result<()> {
+    let measurement = measure_bundle(&read(bundle_path)?)?;
+    let keypair = keypair(seed_path)?;
+    let message = [BUNDLE_SIGNATURE_DOMAIN, &measurement].concat();
+    write(out, &keypair.sign(&message).to_bytes())?;
+    println!("{}", hex(&keypair.public.to_bytes()));
+    Ok(())
+}
+
+fn verify_report(report_path: &str, key: &str, bundle_path: Option<&str>) -> Result<()> {
+    let report = Report::from_bytes(&read(report_path)?).ok_or("malformed report")?;
+    let key = PublicKey::from_bytes(&unhex(key)?).map_err(|e| e.to_string())?;
+    let signature = Signature::from_bytes(&report.signature).map_err(|e| e.to_string())?;
+    key.verify(
+        &Report::signed_bytes(&report.measurement, &report.report_data),
+        &signature,
+    )
+    .map_err(|_| "report signature does not verify".to_string())?;
+    if let Some(bundle_path) = bundle_path {
+        if measure_bundle(&read(bundle_path)?)? != report.measurement {
+            return Err(format!("report is not for {}", bundle_path));
+        }
+    }
+    println!("measurement {}", hex(&report.measurement));
+    println!("report data {}", hex(&report.report_data));
+    Ok(())
+}
+
+fn run(args: &[String]) -> Result<()> {
+    let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    match args[..] {
+        ["pack", elf, manifest, out] => pack(elf, manifest, out),
+        ["measure", bundle] => {
+            println!("{}", hex(&measure_bundle(&read(bundle)?)?));
+            Ok(())
+        }
+        ["keygen", out] => keygen(out),
+        ["sign", bundle, seed, out] => sign(bundle, seed, out),
+        ["verify-report", report, key] => verify_report(report, key, None),
+        ["verify-report", report, key, bundle] => verify_report(report, key, Some(bundle)),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,119 @@
+/* the tool must hash exactly what coffer's loader measures for the same bundle */
+use std::{env, fs, path::PathBuf, process::Command};
+
+use coffer_common::image::Image;
+
+const TEXT_VADDR: u64 = 0x1_0000;
+const DATA_VADDR: u64 = 0x1_1000;
+const MEMORY_SIZE: usize = 0x1_0000;
+
+fn put(elf: &mut Vec<u8>, at: usize, bytes: &[u8]) {
+    if elf.len() < at + bytes.len() {
+        elf.resize(at + bytes.len(), 0);
+    }
+    elf[at..at + bytes.len()].copy_from_slice(bytes);
+}
+
+/* RISC-V ELF64 with a text and a data segment, the data one ends in bss */
+fn sample_elf() -> Vec<u8> {
+    let mut elf = Vec::new();
+    put(&mut elf, 0, b"\x7fELF\x02\x01\x01");
+    put(&mut elf, 16, &3u16.to_le_bytes());
+    put(&mut elf, 18, &243u16.to_le_bytes());
+    put(&mut elf, 20, &1u32.to_le_bytes());
+    put(&mut elf, 24, &TEXT_VADDR.to_le_bytes());
+    put(&mut elf, 32, &64u64.to_le_bytes());
+    put(&mut elf, 52, &64u16.to_le_bytes());
+    put(&mut elf, 54, &56u16.to_le_bytes());
+    put(&mut elf, 56, &2u16.to_le_bytes());
+    put(&mut elf, 58, &64u16.to_le_bytes());
+    /* p_type, p_flags, p_offset, p_vaddr, p_filesz, p_memsz */
+    let segments = [
+        (1u32, 5u32, 0x1000u64, TEXT_VADDR, 0x20u64, 0x20u64),
+        (1, 6, 0x2000, DATA_VADDR, 0x10, 0x800),
+    ];
+    for (i, (kind, flags, offset, vaddr, filesz, memsz)) in segments.iter().enumerate() {
+        let ph = 64 + i * 56;
+        put(&mut elf, ph, &kind.to_le_bytes());
+        put(&mut elf, ph + 4, &flags.to_le_bytes());
+        put(&mut elf, ph + 8, &offset.to_le_bytes());
+        put(&mut elf, ph + 16, &vaddr.to_le_bytes());
+        put(&mut elf, ph + 24, &vaddr.to_le_bytes());
+        put(&mut elf, ph + 32, &filesz.to_le_bytes());
+        put(&mut elf, ph + 40, &memsz.to_le_bytes());
+        put(&mut elf, ph + 48, &0x1000u64.to_le_bytes());
+        let content: Vec<u8> = (0..*filesz as u8).map(|b| b.wrapping_mul(7) | 1).collect();
+        put(&mut elf, *offset as usize, &content);
+    }
+    elf
+}
+
+fn tool(args: &[&PathBuf]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_coffer-tool"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+/* what `loader::load_enclave` does on a region the host left dirty */
+fn firmware_measurement(bundle: &[u8]) -> String {
+    let image = Image::parse(bundle).unwrap();
+    let mut region = vec![0xa5u8; image.manifest.memory_size as usize];
+    image.load_into(&mut region);
+    image
+        .measure(&region)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[test]
+fn tool_measures_what_the_firmware_measures() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("measure");
+    fs::create_dir_all(&dir).unwrap();
+    let elf = dir.join("enclave.elf");
+    let manifest = dir.join("enclave.manifest");
+    let bundle = dir.join("enclave.bundle");
+    fs::write(&elf, sample_elf()).unwrap();
+    fs::write(
+        &manifest,
+        format!(
+            "memory_size = {:#x}\nstack_size = 0x1000\nthreads = 2\nscrub_exempt = 0x4\n",
+            MEMORY_SIZE
+        ),
+    )
+    .unwrap();
+
+    let packed = tool(&[&PathBuf::from("pack"), &elf, &manifest, &bundle]);
+    let measured = tool(&[&PathBuf::from("measure"), &bundle]);
+    let expected = firmware_measurement(&fs::read(&bundle).unwrap());
+    assert_eq!(packed, expected);
+    assert_eq!(measured, expected);
+}
+
+#[test]
+fn manifest_is_part_of_the_measurement() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("manifest");
+    fs::create_dir_all(&dir).unwrap();
+    let elf = dir.join("enclave.elf");
+    fs::write(&elf, sample_elf()).unwrap();
+    let mut measurements = Vec::new();
+    for exempt in ["0x0", "0x1"].iter() {
+        let manifest = dir.join(format!("{}.manifest", exempt));
+        let bundle = dir.join(format!("{}.bundle", exempt));
+        fs::write(
+            &manifest,
+            format!("memory_size = {:#x}\nscrub_exempt = {}\n", MEMORY_SIZE, exempt),
+        )
+        .unwrap();
+        tool(&[&PathBuf::from("pack"), &elf, &manifest, &bundle]);
+        measurements.push(firmware_measurement(&fs::read(&bundle).unwrap()));
+    }
+    assert_ne!(measurements[0], measurements[1]);
+}